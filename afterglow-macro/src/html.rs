@@ -0,0 +1,627 @@
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote, quote_spanned};
+use std::cell::Cell;
+use syn::ext::IdentExt;
+use syn::parse::{Parse, ParseStream};
+use syn::{braced, token, Expr, Ident, LitStr, Pat, Result, Token};
+
+/// `html!(bump, [sender,] <root ...>...</root>)`, or `<>...</>` for several root nodes.
+pub struct Html {
+    bump: Expr,
+    sender: Option<Expr>,
+    root: Element,
+}
+
+enum Child {
+    Element(Element),
+    Text(LitStr),
+    Expr(Expr),
+    If(IfBlock),
+    For(ForBlock),
+}
+
+/// An element, or a fragment `<>...</>` when the name is empty.
+struct Element {
+    name: Name,
+    attrs: Vec<Attr>,
+    children: Vec<Child>,
+}
+
+impl Element {
+    fn is_fragment(&self) -> bool {
+        self.name.value.is_empty()
+    }
+}
+
+struct IfBlock {
+    cond: Expr,
+    then: Vec<Child>,
+    otherwise: Option<Else>,
+}
+
+enum Else {
+    If(Box<IfBlock>),
+    Block(Vec<Child>),
+}
+
+struct ForBlock {
+    pat: Pat,
+    iter: Expr,
+    body: Vec<Child>,
+}
+
+/// A possibly dashed name such as `div`, `my-widget` or `aria-label`.
+struct Name {
+    value: String,
+    span: Span,
+}
+
+enum Attr {
+    Static(Name, LitStr),
+    Dynamic(Name, Expr),
+    Bool(Name),
+    /// `on:event={|e| Msg}`, bound to the sender like `consume`.
+    Message(Name, Expr),
+    /// `listen:event={listener}`, a raw dodrio listener such as `route_to`.
+    Listen(Name, Expr),
+    Key(Expr),
+}
+
+impl Parse for Html {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let bump = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let sender = if input.peek(Token![<]) {
+            None
+        } else {
+            let sender = input.parse()?;
+            input.parse::<Token![,]>()?;
+            Some(sender)
+        };
+        let root = input.parse()?;
+        if !input.is_empty() {
+            return Err(
+                input.error("html! expects a single root element, wrap siblings in <>...</>")
+            );
+        }
+        Ok(Html { bump, sender, root })
+    }
+}
+
+impl Parse for Name {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let first = Ident::parse_any(input)?;
+        let span = first.span();
+        let mut value = first.to_string();
+        while input.peek(Token![-]) {
+            input.parse::<Token![-]>()?;
+            value.push('-');
+            value.push_str(&Ident::parse_any(input)?.to_string());
+        }
+        Ok(Name { value, span })
+    }
+}
+
+/// The name of an element, empty for a fragment's `<>` and `</>`.
+fn parse_tag_name(input: ParseStream) -> Result<Name> {
+    if input.peek(Token![>]) {
+        Ok(Name {
+            value: String::new(),
+            span: input.span(),
+        })
+    } else {
+        input.parse()
+    }
+}
+
+impl Parse for Element {
+    fn parse(input: ParseStream) -> Result<Self> {
+        input.parse::<Token![<]>()?;
+        let name = parse_tag_name(input)?;
+
+        let mut attrs = vec![];
+        while !(input.peek(Token![>]) || input.peek(Token![/])) {
+            attrs.push(input.parse()?);
+        }
+
+        if input.peek(Token![/]) {
+            input.parse::<Token![/]>()?;
+            input.parse::<Token![>]>()?;
+            return Ok(Element {
+                name,
+                attrs,
+                children: vec![],
+            });
+        }
+        input.parse::<Token![>]>()?;
+
+        let mut children = vec![];
+        while !(input.peek(Token![<]) && input.peek2(Token![/])) {
+            if input.is_empty() {
+                return Err(syn::Error::new(
+                    name.span,
+                    format!("unclosed element <{}>", name.value),
+                ));
+            }
+            children.push(input.parse()?);
+        }
+        input.parse::<Token![<]>()?;
+        input.parse::<Token![/]>()?;
+        let closing = parse_tag_name(input)?;
+        if closing.value != name.value {
+            return Err(syn::Error::new(
+                closing.span,
+                format!("expected </{}>", name.value),
+            ));
+        }
+        input.parse::<Token![>]>()?;
+
+        Ok(Element {
+            name,
+            attrs,
+            children,
+        })
+    }
+}
+
+impl Parse for Attr {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let name: Name = input.parse()?;
+        if input.peek(Token![:]) {
+            input.parse::<Token![:]>()?;
+            let event: Name = input.parse()?;
+            input.parse::<Token![=]>()?;
+            let expr = parse_braced_expr(input)?;
+            return match name.value.as_str() {
+                "on" => Ok(Attr::Message(event, expr)),
+                "listen" => Ok(Attr::Listen(event, expr)),
+                _ => Err(syn::Error::new(
+                    name.span,
+                    "expected `on:` or `listen:` before an event name",
+                )),
+            };
+        }
+
+        if !input.peek(Token![=]) {
+            check_attr(&name)?;
+            return Ok(Attr::Bool(name));
+        }
+        input.parse::<Token![=]>()?;
+
+        if name.value == "key" {
+            return Ok(Attr::Key(parse_braced_expr(input)?));
+        }
+        check_attr(&name)?;
+        if input.peek(LitStr) {
+            Ok(Attr::Static(name, input.parse()?))
+        } else {
+            Ok(Attr::Dynamic(name, parse_braced_expr(input)?))
+        }
+    }
+}
+
+impl Parse for Child {
+    fn parse(input: ParseStream) -> Result<Self> {
+        if input.peek(Token![<]) {
+            Ok(Child::Element(input.parse()?))
+        } else if input.peek(LitStr) {
+            Ok(Child::Text(input.parse()?))
+        } else if input.peek(token::Brace) {
+            Ok(Child::Expr(parse_braced_expr(input)?))
+        } else if input.peek(Token![if]) {
+            Ok(Child::If(input.parse()?))
+        } else if input.peek(Token![for]) {
+            Ok(Child::For(input.parse()?))
+        } else {
+            Err(input.error("expected an element, a string literal, `{expr}`, `if` or `for`"))
+        }
+    }
+}
+
+impl Parse for IfBlock {
+    fn parse(input: ParseStream) -> Result<Self> {
+        input.parse::<Token![if]>()?;
+        let cond = Expr::parse_without_eager_brace(input)?;
+        let then = parse_block(input)?;
+        let otherwise = if input.peek(Token![else]) {
+            input.parse::<Token![else]>()?;
+            if input.peek(Token![if]) {
+                Some(Else::If(Box::new(input.parse()?)))
+            } else {
+                Some(Else::Block(parse_block(input)?))
+            }
+        } else {
+            None
+        };
+        Ok(IfBlock {
+            cond,
+            then,
+            otherwise,
+        })
+    }
+}
+
+impl Parse for ForBlock {
+    fn parse(input: ParseStream) -> Result<Self> {
+        input.parse::<Token![for]>()?;
+        let pat = Pat::parse_single(input)?;
+        input.parse::<Token![in]>()?;
+        let iter = Expr::parse_without_eager_brace(input)?;
+        let body = parse_block(input)?;
+        Ok(ForBlock { pat, iter, body })
+    }
+}
+
+fn parse_braced_expr(input: ParseStream) -> Result<Expr> {
+    let content;
+    braced!(content in input);
+    content.parse()
+}
+
+fn parse_block(input: ParseStream) -> Result<Vec<Child>> {
+    let content;
+    braced!(content in input);
+    let mut children = vec![];
+    while !content.is_empty() {
+        children.push(content.parse()?);
+    }
+    Ok(children)
+}
+
+const GLOBAL_ATTRS: &[&str] = &[
+    "accesskey",
+    "autocapitalize",
+    "autofocus",
+    "class",
+    "contenteditable",
+    "dir",
+    "draggable",
+    "enterkeyhint",
+    "hidden",
+    "id",
+    "inputmode",
+    "is",
+    "itemid",
+    "itemprop",
+    "itemref",
+    "itemscope",
+    "itemtype",
+    "lang",
+    "nonce",
+    "part",
+    "role",
+    "slot",
+    "spellcheck",
+    "style",
+    "tabindex",
+    "title",
+    "translate",
+];
+
+const ELEMENT_ATTRS: &[&str] = &[
+    "accept",
+    "accept-charset",
+    "action",
+    "allow",
+    "alt",
+    "async",
+    "autocomplete",
+    "autoplay",
+    "capture",
+    "charset",
+    "checked",
+    "cite",
+    "cols",
+    "colspan",
+    "content",
+    "controls",
+    "coords",
+    "crossorigin",
+    "data",
+    "datetime",
+    "decoding",
+    "default",
+    "defer",
+    "dirname",
+    "disabled",
+    "download",
+    "enctype",
+    "for",
+    "form",
+    "formaction",
+    "formenctype",
+    "formmethod",
+    "formnovalidate",
+    "formtarget",
+    "headers",
+    "height",
+    "high",
+    "href",
+    "hreflang",
+    "http-equiv",
+    "integrity",
+    "ismap",
+    "kind",
+    "label",
+    "list",
+    "loading",
+    "loop",
+    "low",
+    "max",
+    "maxlength",
+    "media",
+    "method",
+    "min",
+    "minlength",
+    "multiple",
+    "muted",
+    "name",
+    "novalidate",
+    "open",
+    "optimum",
+    "pattern",
+    "ping",
+    "placeholder",
+    "playsinline",
+    "poster",
+    "preload",
+    "readonly",
+    "referrerpolicy",
+    "rel",
+    "required",
+    "reversed",
+    "rows",
+    "rowspan",
+    "sandbox",
+    "scope",
+    "selected",
+    "shape",
+    "size",
+    "sizes",
+    "span",
+    "src",
+    "srcdoc",
+    "srclang",
+    "srcset",
+    "start",
+    "step",
+    "target",
+    "type",
+    "usemap",
+    "value",
+    "width",
+    "wrap",
+];
+
+const SVG_ATTRS: &[&str] = &[
+    "clip-path",
+    "clip-rule",
+    "cx",
+    "cy",
+    "d",
+    "fill",
+    "fill-opacity",
+    "fill-rule",
+    "font-family",
+    "font-size",
+    "opacity",
+    "points",
+    "preserveAspectRatio",
+    "r",
+    "rx",
+    "ry",
+    "stroke",
+    "stroke-dasharray",
+    "stroke-linecap",
+    "stroke-linejoin",
+    "stroke-opacity",
+    "stroke-width",
+    "text-anchor",
+    "transform",
+    "viewBox",
+    "x",
+    "x1",
+    "x2",
+    "xmlns",
+    "y",
+    "y1",
+    "y2",
+];
+
+fn check_attr(name: &Name) -> Result<()> {
+    let value = name.value.as_str();
+    let known = value.starts_with("data-")
+        || value.starts_with("aria-")
+        || GLOBAL_ATTRS.contains(&value)
+        || ELEMENT_ATTRS.contains(&value)
+        || SVG_ATTRS.contains(&value);
+    if known {
+        Ok(())
+    } else {
+        Err(syn::Error::new(
+            name.span,
+            format!(
+                "unknown attribute `{}`, use a `data-` prefix for custom attributes",
+                value
+            ),
+        ))
+    }
+}
+
+struct Codegen {
+    bump: Ident,
+    sender: Option<Ident>,
+    counter: Cell<usize>,
+}
+
+impl Codegen {
+    fn ident(&self, name: &str) -> Ident {
+        let id = self.counter.get();
+        self.counter.set(id + 1);
+        format_ident!("__{}_{}", name, id, span = Span::mixed_site())
+    }
+
+    fn element(&self, element: &Element) -> TokenStream {
+        let bump = &self.bump;
+        let el = self.ident("el");
+        let children = self.ident("children");
+        let tag = &element.name.value;
+
+        let constructor = if tag.contains('-') {
+            quote_spanned! {element.name.span=>
+                ::afterglow::dodrio::builder::ElementBuilder::new(#bump, #tag)
+            }
+        } else {
+            let tag = Ident::new(tag, element.name.span);
+            quote_spanned! {element.name.span=> ::afterglow::dodrio::builder::#tag(#bump) }
+        };
+
+        let attrs = element.attrs.iter().map(|attr| self.attr(&el, attr));
+        let nodes = element
+            .children
+            .iter()
+            .map(|child| self.child(&children, child));
+
+        quote! {{
+            let mut #el = #constructor;
+            #(#attrs)*
+            let mut #children = ::afterglow::dodrio::bumpalo::collections::Vec::new_in(#bump);
+            #(#nodes)*
+            #el.children(#children).finish()
+        }}
+    }
+
+    fn attr(&self, el: &Ident, attr: &Attr) -> TokenStream {
+        let bump = &self.bump;
+        match attr {
+            Attr::Static(name, value) => {
+                let name = &name.value;
+                quote! { #el = #el.attr(#name, #value); }
+            }
+            Attr::Bool(name) => {
+                let name = &name.value;
+                quote! { #el = #el.attr(#name, ""); }
+            }
+            Attr::Dynamic(name, expr) => {
+                let name = &name.value;
+                quote! {
+                    if let ::std::option::Option::Some(value) =
+                        ::afterglow::html::IntoAttr::into_attr(#expr, #bump)
+                    {
+                        #el = #el.attr(#name, value);
+                    }
+                }
+            }
+            Attr::Message(event, expr) => {
+                let name = &event.value;
+                match self.sender.as_ref() {
+                    Some(sender) => quote! {
+                        #el = #el.on(#name, ::afterglow::messenger::consume(#expr, #sender));
+                    },
+                    None => syn::Error::new(
+                        event.span,
+                        "`on:` bindings need a sender, use html!(bump, sender, ...)",
+                    )
+                    .to_compile_error(),
+                }
+            }
+            Attr::Listen(event, expr) => {
+                let name = &event.value;
+                quote! { #el = #el.on(#name, #expr); }
+            }
+            Attr::Key(expr) => quote! { #el = #el.key(#expr); },
+        }
+    }
+
+    fn child(&self, nodes: &Ident, child: &Child) -> TokenStream {
+        let bump = &self.bump;
+        match child {
+            Child::Element(element) if element.is_fragment() => {
+                let children = element
+                    .children
+                    .iter()
+                    .map(|child| self.child(nodes, child));
+                quote! { #(#children)* }
+            }
+            Child::Element(element) => {
+                let element = self.element(element);
+                quote! { #nodes.push(#element); }
+            }
+            Child::Text(text) => quote! { #nodes.push(::afterglow::dodrio::builder::text(#text)); },
+            Child::Expr(expr) => quote! {
+                ::afterglow::html::IntoNodes::into_nodes(#expr, #bump, &mut #nodes);
+            },
+            Child::If(block) => self.if_block(nodes, block),
+            Child::For(ForBlock { pat, iter, body }) => {
+                let body = body.iter().map(|child| self.child(nodes, child));
+                quote! {
+                    for #pat in #iter {
+                        #(#body)*
+                    }
+                }
+            }
+        }
+    }
+
+    fn if_block(&self, nodes: &Ident, block: &IfBlock) -> TokenStream {
+        let cond = &block.cond;
+        let then = block.then.iter().map(|child| self.child(nodes, child));
+        let otherwise = match block.otherwise.as_ref() {
+            None => quote! {},
+            Some(Else::If(block)) => {
+                let block = self.if_block(nodes, block);
+                quote! { else { #block } }
+            }
+            Some(Else::Block(children)) => {
+                let children = children.iter().map(|child| self.child(nodes, child));
+                quote! { else { #(#children)* } }
+            }
+        };
+        quote! {
+            if #cond {
+                #(#then)*
+            } #otherwise
+        }
+    }
+}
+
+pub fn expand(input: Html) -> TokenStream {
+    let codegen = Codegen {
+        bump: format_ident!("__bump", span = Span::mixed_site()),
+        sender: input
+            .sender
+            .as_ref()
+            .map(|_| format_ident!("__sender", span = Span::mixed_site())),
+        counter: Cell::new(0),
+    };
+    let bump = &codegen.bump;
+    let bump_expr = &input.bump;
+    let sender = match (codegen.sender.as_ref(), input.sender.as_ref()) {
+        (Some(ident), Some(expr)) => quote! {
+            let #ident: &::afterglow::messenger::MessageSender<_> = &#expr;
+        },
+        _ => quote! {},
+    };
+    let root = if input.root.is_fragment() {
+        let children = codegen.ident("children");
+        let nodes = input
+            .root
+            .children
+            .iter()
+            .map(|child| codegen.child(&children, child));
+        quote! {{
+            let mut #children = ::afterglow::dodrio::bumpalo::collections::Vec::new_in(#bump);
+            #(#nodes)*
+            #children
+        }}
+    } else {
+        codegen.element(&input.root)
+    };
+
+    quote! {{
+        let #bump: &::afterglow::dodrio::bumpalo::Bump =
+            ::std::convert::Into::into(#bump_expr);
+        #sender
+        #root
+    }}
+}
@@ -0,0 +1,46 @@
+extern crate proc_macro;
+
+mod html;
+mod messenger;
+
+use proc_macro::TokenStream;
+use syn::{parse_macro_input, DeriveInput};
+
+/// Build dodrio nodes with a jsx-like syntax, see `afterglow::html` for details.
+#[proc_macro]
+pub fn html(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as html::Html);
+    html::expand(input).into()
+}
+
+/// Implement `Messenger` by forwarding each variant to a handler method on the target.
+///
+/// ```ignore
+/// #[derive(Clone, Messenger)]
+/// #[messenger(target = Model, bus = BusEvent::Click, receive)]
+/// pub enum ClickMsg {
+///     // calls `Model::on_clicked(&mut self) -> bool`
+///     Clicked,
+///     // calls `Model::on_set_value(&mut self, value: i32) -> bool`
+///     SetValue(i32),
+///     // calls `Model::moved(&mut self, x, y, sender, render_tx) -> bool`
+///     #[messenger(handler = moved, context)]
+///     Move { x: i32, y: i32 },
+/// }
+/// ```
+///
+/// - `target` is the `Messenger::Target`, required.
+/// - `bus` names a variant of a bus event wrapping this messenger and generates
+///   `From<ClickMsg> for BusEvent`.
+/// - `receive` also generates the `Into<Option<Message<Model>>>` conversion of the bus event needed
+///   by `BusService::subscribe`, routing only this variant. There can only be one per target and
+///   bus event, write it by hand to route the variants of several messengers.
+/// - `handler` overrides the default `on_<variant>` method name.
+/// - `context` also passes the sender and render sender to the handler.
+#[proc_macro_derive(Messenger, attributes(messenger))]
+pub fn derive_messenger(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    messenger::expand(input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
@@ -0,0 +1,237 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::spanned::Spanned;
+use syn::{Data, DeriveInput, Fields, Ident, Path, Result, Type};
+
+struct Options {
+    target: Type,
+    bus: Option<Path>,
+    receive: bool,
+}
+
+struct VariantOptions {
+    handler: Option<Ident>,
+    context: bool,
+}
+
+fn parse_options(input: &DeriveInput) -> Result<Options> {
+    let mut target = None;
+    let mut bus = None;
+    let mut receive = false;
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|a| a.path().is_ident("messenger"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("target") {
+                target = Some(meta.value()?.parse()?);
+                Ok(())
+            } else if meta.path.is_ident("bus") {
+                bus = Some(meta.value()?.parse()?);
+                Ok(())
+            } else if meta.path.is_ident("receive") {
+                receive = true;
+                Ok(())
+            } else {
+                Err(meta.error("expected `target`, `bus` or `receive`"))
+            }
+        })?;
+    }
+    let target = target.ok_or_else(|| {
+        syn::Error::new(
+            input.ident.span(),
+            "missing #[messenger(target = Model)] on the enum",
+        )
+    })?;
+    if receive && bus.is_none() {
+        return Err(syn::Error::new(
+            input.ident.span(),
+            "`receive` needs a bus variant, e.g. #[messenger(bus = BusEvent::Click, receive)]",
+        ));
+    }
+    Ok(Options {
+        target,
+        bus,
+        receive,
+    })
+}
+
+fn parse_variant_options(attrs: &[syn::Attribute]) -> Result<VariantOptions> {
+    let mut options = VariantOptions {
+        handler: None,
+        context: false,
+    };
+    for attr in attrs.iter().filter(|a| a.path().is_ident("messenger")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("handler") {
+                options.handler = Some(meta.value()?.parse()?);
+                Ok(())
+            } else if meta.path.is_ident("context") {
+                options.context = true;
+                Ok(())
+            } else {
+                Err(meta.error("expected `handler` or `context`"))
+            }
+        })?;
+    }
+    Ok(options)
+}
+
+/// `SetValue` -> `set_value`
+fn snake_case(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut out = String::new();
+    for (i, c) in chars.iter().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            let prev = chars[i - 1];
+            let next_lower = chars.get(i + 1).is_some_and(|n| n.is_lowercase());
+            if prev.is_lowercase() || prev.is_numeric() || (prev.is_uppercase() && next_lower) {
+                out.push('_');
+            }
+        }
+        out.extend(c.to_lowercase());
+    }
+    out
+}
+
+pub fn expand(input: DeriveInput) -> Result<TokenStream> {
+    let options = parse_options(&input)?;
+    let data = match &input.data {
+        Data::Enum(data) => data,
+        _ => {
+            return Err(syn::Error::new(
+                input.ident.span(),
+                "Messenger can only be derived for enums",
+            ))
+        }
+    };
+
+    let name = &input.ident;
+    let target = &options.target;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let mut arms = vec![];
+    for variant in data.variants.iter() {
+        let variant_options = parse_variant_options(&variant.attrs)?;
+        let ident = &variant.ident;
+        let handler = variant_options
+            .handler
+            .unwrap_or_else(|| format_ident!("on_{}", snake_case(&ident.to_string())));
+
+        let (pattern, args) = match &variant.fields {
+            Fields::Unit => (quote! {}, vec![]),
+            Fields::Unnamed(fields) => {
+                let args: Vec<_> = (0..fields.unnamed.len())
+                    .map(|i| format_ident!("field_{}", i))
+                    .collect();
+                (quote! { ( #(#args),* ) }, args)
+            }
+            Fields::Named(fields) => {
+                // bind to generated names, fields may be called `target`, `sender` or `render_tx`
+                let names = fields.named.iter().map(|field| &field.ident);
+                let args: Vec<_> = (0..fields.named.len())
+                    .map(|i| format_ident!("__field_{}", i))
+                    .collect();
+                (quote! { { #(#names: #args),* } }, args)
+            }
+        };
+        let context = if variant_options.context {
+            quote! { sender, render_tx }
+        } else {
+            quote! {}
+        };
+        let call = if args.is_empty() || context.is_empty() {
+            quote! { target.#handler(#(#args),* #context) }
+        } else {
+            quote! { target.#handler(#(#args),*, #context) }
+        };
+        arms.push(quote! { #name::#ident #pattern => #call, });
+    }
+
+    let messenger = quote! {
+        impl #impl_generics ::afterglow::messenger::Messenger for #name #ty_generics #where_clause {
+            type Target = #target;
+
+            fn update(
+                self: ::std::boxed::Box<Self>,
+                target: &mut Self::Target,
+                sender: &::afterglow::messenger::MessageSender<Self::Target>,
+                render_tx: &::afterglow::prelude::Sender<(
+                    (),
+                    ::afterglow::prelude::oneshot::Sender<()>,
+                )>,
+            ) -> bool {
+                let _ = (&sender, &render_tx);
+                match *self {
+                    #(#arms)*
+                }
+            }
+        }
+    };
+
+    let bus = match options.bus.as_ref() {
+        None => quote! {},
+        Some(variant) => {
+            if !input.generics.params.is_empty() {
+                return Err(syn::Error::new(
+                    input.generics.span(),
+                    "bus conversions can't be derived for generic messengers",
+                ));
+            }
+            if variant.segments.len() < 2 {
+                return Err(syn::Error::new(
+                    variant.span(),
+                    "expected a bus variant such as `BusEvent::Click`",
+                ));
+            }
+            let mut event = variant.clone();
+            event.segments.pop();
+            event.segments.pop_punct();
+            let receive = if options.receive {
+                quote! {
+                    impl ::std::convert::From<#event>
+                        for ::std::option::Option<::afterglow::messenger::Message<#target>>
+                    {
+                        #[allow(unreachable_patterns)]
+                        fn from(event: #event) -> Self {
+                            match event {
+                                #variant(msg) => ::std::option::Option::Some(::std::boxed::Box::new(msg)),
+                                _ => ::std::option::Option::None,
+                            }
+                        }
+                    }
+                }
+            } else {
+                quote! {}
+            };
+            quote! {
+                impl ::std::convert::From<#name> for #event {
+                    fn from(msg: #name) -> Self {
+                        #variant(msg)
+                    }
+                }
+
+                #receive
+            }
+        }
+    };
+
+    Ok(quote! {
+        #messenger
+        #bus
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::snake_case;
+
+    #[test]
+    fn variant_names_to_handlers() {
+        assert_eq!(snake_case("Clicked"), "clicked");
+        assert_eq!(snake_case("SetValue"), "set_value");
+        assert_eq!(snake_case("HTTPError"), "http_error");
+        assert_eq!(snake_case("Page2Loaded"), "page2_loaded");
+    }
+}
@@ -13,7 +13,7 @@ where
     R: Renderer<Target = T, Data = T>,
 {
     fn default() -> Self {
-        Route(PhantomData::default())
+        Route(PhantomData)
     }
 }
 
@@ -41,19 +41,16 @@ where
 }
 
 pub struct Router {
-    path: Option<String>,
     pub entry: Option<Entry>,
     pub routes: HashMap<String, Box<dyn Routable>>,
     rx: Receiver<RouteEvent>,
-    onpopstate: EventListener,
-    onroutechange: EventListener,
+    _onpopstate: EventListener,
+    _onroutechange: EventListener,
+    transition: Option<Transition>,
 }
 
 impl Default for Router {
     fn default() -> Self {
-        let mut state = HashMap::<&str, &str>::new();
-        state.insert("path", "");
-
         let win = web_sys::window()
             .unwrap()
             .unchecked_into::<web_sys::EventTarget>();
@@ -83,12 +80,12 @@ impl Default for Router {
         });
 
         Router {
-            path: None,
             entry: None,
             routes: HashMap::new(),
             rx,
-            onpopstate,
-            onroutechange,
+            _onpopstate: onpopstate,
+            _onroutechange: onroutechange,
+            transition: None,
         }
     }
 }
@@ -104,14 +101,27 @@ impl Router {
         self
     }
 
+    /// Animate the old route out and the new one in, see `afterglow::transition`.
+    pub fn transition(mut self, transition: Transition) -> Self {
+        self.transition = Some(transition);
+        self
+    }
+
     pub async fn routing(&mut self, path: &str, tag: Option<&str>) -> bool {
         if let Some(route) = self.routes.get(path) {
+            let block = self.transition.and_then(|_| route_block(tag));
             if let Some(old_entry) = self.entry.as_mut() {
+                if let (Some(transition), Some(block)) = (self.transition, block.as_ref()) {
+                    transition.leave(block).await;
+                }
                 let (tx, rx) = oneshot::channel::<()>();
                 let _ = old_entry.msg_tx.send(EntryMessage::Eject(tx)).await;
                 let _ = rx.await;
             }
             self.entry.replace(route.serve(tag).await);
+            if let (Some(transition), Some(block)) = (self.transition, block) {
+                spawn_local(async move { transition.enter(&block).await });
+            }
             true
         } else {
             false
@@ -130,10 +140,11 @@ impl Router {
                 RouteEvent::Native(e) => {
                     log::info!("browser routing");
                     let e = e.unchecked_into::<web_sys::PopStateEvent>();
-                    if let Ok(state) = JsValue::into_serde::<HashMap<String, String>>(&e.state()) {
-                        if let Some(path) = state.get("path") {
-                            self.routing(path, tag).await;
-                        }
+                    let path = js_sys::Reflect::get(&e.state(), &JsValue::from_str("path"))
+                        .ok()
+                        .and_then(|path| path.as_string());
+                    if let Some(path) = path {
+                        self.routing(&path, tag).await;
                     }
                 }
 
@@ -141,14 +152,11 @@ impl Router {
                 RouteEvent::Manual(path) => {
                     log::info!("manual routing");
                     if self.routing(&path, tag).await {
-                        let mut state = HashMap::new();
-                        state.insert("path", path.clone());
+                        let state = js_sys::Object::new();
+                        js_sys::Reflect::set(&state, &"path".into(), &JsValue::from_str(&path))
+                            .unwrap();
                         history
-                            .push_state_with_url(
-                                &JsValue::from_serde(&state).unwrap(),
-                                "",
-                                Some(&path),
-                            )
+                            .push_state_with_url(&state, "", Some(&path))
                             .unwrap();
                     }
                 }
@@ -157,12 +165,21 @@ impl Router {
     }
 }
 
+/// The element routes are mounted in, same as `Entry::init_app`.
+fn route_block(tag: Option<&str>) -> Option<web_sys::Element> {
+    let document = web_sys::window()?.document()?;
+    match tag {
+        Some(id) => document.get_element_by_id(id),
+        None => document.body().map(Into::into),
+    }
+}
+
 pub fn emit_route(path: &str) {
     let win = web_sys::window().unwrap();
     let target = win.unchecked_into::<web_sys::EventTarget>();
 
-    let mut init = web_sys::CustomEventInit::new();
-    init.detail(&JsValue::from_str(path));
+    let init = web_sys::CustomEventInit::new();
+    init.set_detail(&JsValue::from_str(path));
     let event = web_sys::CustomEvent::new_with_event_init_dict("routechange", &init).unwrap();
     target
         .dispatch_event(&event.unchecked_into::<web_sys::Event>())
@@ -177,6 +194,14 @@ pub fn route_to(path: &str) -> impl Fn(&mut dyn RootRender, VdomWeak, Event) + '
     }
 }
 
+/// Same as `route_to`, for views built with `afterglow::vnode`.
+pub fn navigate(path: &str) -> impl Fn(Event) + 'static {
+    let path = path.to_string();
+    move |_| {
+        emit_route(&path);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -185,7 +210,7 @@ mod tests {
     wasm_bindgen_test_configure!(run_in_browser);
     pub struct Model;
     impl LifeCycle for Model {
-        fn new(render_tx: Sender<((), oneshot::Sender<()>)>) -> Self {
+        fn new(_render_tx: Sender<((), oneshot::Sender<()>)>) -> Self {
             Model
         }
     }
@@ -252,7 +277,7 @@ mod tests {
                             .attr("class", "box")
                             .children(
                                 target
-                                    .model
+                                    .dummy
                                     .as_ref()
                                     .map(|v| v.render(ctx))
                                     .into_iter()
@@ -261,7 +286,7 @@ mod tests {
                             .finish(),
                         dodrio::builder::a(bump)
                             .attr("class", "button")
-                            .on("click", consume(|e| MegaMsg::RemoveMega, &sender))
+                            .on("click", consume(|_e| MegaMsg::RemoveMega, sender))
                             .child(text("remove model"))
                             .finish(),
                     ])
@@ -272,24 +297,21 @@ mod tests {
 
     pub enum MegaMsg {
         RemoveMega,
-        Clicked,
     }
     impl Messenger for MegaMsg {
         type Target = Mega;
         fn update(
             self: Box<Self>,
             target: &mut Self::Target,
-            sender: &MessageSender<Self::Target>,
-            render_tx: &Sender<((), oneshot::Sender<()>)>,
+            _sender: &MessageSender<Self::Target>,
+            _render_tx: &Sender<((), oneshot::Sender<()>)>,
         ) -> bool {
             match *self {
                 MegaMsg::RemoveMega => {
                     target.model = None;
-                    return true;
+                    true
                 }
-                _ => {}
             }
-            false
         }
     }
 
@@ -301,9 +323,9 @@ mod tests {
 
         fn view<'a>(
             &self,
-            target: &Self::Target,
+            _target: &Self::Target,
             ctx: &mut RenderContext<'a>,
-            sender: &MessageSender<Self::Data>,
+            _sender: &MessageSender<Self::Data>,
         ) -> Node<'a> {
             let bump = ctx.bump;
 
@@ -314,12 +336,10 @@ mod tests {
     }
 
     #[derive(Default)]
-    pub struct Dummy {
-        pub arr: [[[i32; 10]; 10]; 10],
-    }
+    pub struct Dummy;
     impl LifeCycle for Dummy {
-        fn new(render_tx: Sender<((), oneshot::Sender<()>)>) -> Self {
-            Dummy::default()
+        fn new(_render_tx: Sender<((), oneshot::Sender<()>)>) -> Self {
+            Dummy
         }
     }
 
@@ -331,9 +351,9 @@ mod tests {
 
         fn view<'a>(
             &self,
-            target: &Self::Target,
+            _target: &Self::Target,
             ctx: &mut RenderContext<'a>,
-            sender: &MessageSender<Self::Data>,
+            _sender: &MessageSender<Self::Data>,
         ) -> Node<'a> {
             let bump = ctx.bump;
             dodrio::builder::div(bump)
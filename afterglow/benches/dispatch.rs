@@ -0,0 +1,98 @@
+//! Cost of getting a message from an event handler into a container's message loop.
+//!
+//! Run with `cargo bench -p afterglow --bench dispatch`.
+
+use afterglow::prelude::*;
+use futures::executor::LocalPool;
+use futures::task::LocalSpawnExt;
+use std::time::{Duration, Instant};
+
+const MESSAGES: usize = 200_000;
+
+#[derive(Default)]
+struct Pointer {
+    x: i32,
+    y: i32,
+}
+
+struct Moved(i32, i32);
+
+impl Messenger for Moved {
+    type Target = Pointer;
+
+    fn update(
+        self: Box<Self>,
+        target: &mut Self::Target,
+        _sender: &MessageSender<Self::Target>,
+        _render_tx: &Sender<((), oneshot::Sender<()>)>,
+    ) -> bool {
+        target.x = self.0;
+        target.y = self.1;
+        true
+    }
+}
+
+/// Run a message loop like `Container::init_messenger` while `emit` produces the messages.
+fn run(emit: impl FnOnce(&LocalPool, &MessageSender<Pointer>)) -> Duration {
+    let mut pool = LocalPool::new();
+    let (tx, rx) = mpsc::unbounded::<(Message<Pointer>, Ack)>();
+    let (render_tx, _render_rx) = mpsc::unbounded::<((), oneshot::Sender<()>)>();
+    let handle = tx.clone();
+
+    let start = Instant::now();
+    pool.spawner()
+        .spawn_local(async move {
+            let mut pointer = Pointer::default();
+            rx.take(MESSAGES)
+                .for_each(|(msg, ack)| {
+                    msg.update(&mut pointer, &handle, &render_tx);
+                    if let Some(ack) = ack {
+                        let _ = ack.send(());
+                    }
+                    async {}
+                })
+                .await;
+        })
+        .unwrap();
+    emit(&pool, &tx);
+    pool.run();
+    start.elapsed()
+}
+
+/// What `consume` used to do, spawn a task per message that waits for it's acknowledgement.
+fn spawned() -> Duration {
+    run(|pool, tx| {
+        for i in 0..MESSAGES as i32 {
+            let mut tx = tx.clone();
+            pool.spawner()
+                .spawn_local(async move {
+                    let (ack, done) = oneshot::channel::<()>();
+                    let _ = tx.send((Box::new(Moved(i, i)), Some(ack))).await;
+                    let _ = done.await;
+                })
+                .unwrap();
+        }
+    })
+}
+
+/// What `consume` does now through `Messenger::send`.
+fn sent() -> Duration {
+    run(|_, tx| {
+        for i in 0..MESSAGES as i32 {
+            Moved(i, i).send(tx);
+        }
+    })
+}
+
+fn report(name: &str, elapsed: Duration) {
+    let per_msg = elapsed.as_nanos() as f64 / MESSAGES as f64;
+    println!("{:<12} {:>10.2?} {:>10.1} ns/msg", name, elapsed, per_msg);
+}
+
+fn main() {
+    spawned();
+    sent();
+
+    report("spawned", spawned());
+    report("send", sent());
+}
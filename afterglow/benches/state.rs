@@ -0,0 +1,90 @@
+//! Message throughput of the container's state storage.
+//!
+//! Run with `cargo bench -p afterglow --bench state`.
+
+use afterglow::state::State;
+use futures::channel::{mpsc, oneshot};
+use futures::executor::LocalPool;
+use futures::lock::Mutex;
+use futures::prelude::*;
+use futures::task::LocalSpawnExt;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+const MESSAGES: usize = 200_000;
+
+#[derive(Default)]
+struct Grid {
+    cells: Vec<u64>,
+}
+
+fn update(grid: &mut Grid, i: usize) -> bool {
+    if grid.cells.len() < 64 {
+        grid.cells.resize(64, 0);
+    }
+    grid.cells[i % 64] += 1;
+    i.is_multiple_of(2)
+}
+
+/// The message loop before, locking an async mutex per message.
+fn mutex_loop() -> Duration {
+    let data = Rc::new(Mutex::new(Grid::default()));
+    run_loop(move |i| {
+        let data = data.clone();
+        async move {
+            let mut grid = data.lock().await;
+            update(&mut grid, i)
+        }
+        .boxed_local()
+    })
+}
+
+/// The message loop now, borrowing the state cell per message.
+fn state_loop() -> Duration {
+    let data = Rc::new(State::new(Grid::default()));
+    run_loop(move |i| {
+        let data = data.clone();
+        async move { data.update(|grid| update(grid, i)).unwrap_or(false) }.boxed_local()
+    })
+}
+
+fn run_loop(handle: impl Fn(usize) -> future::LocalBoxFuture<'static, bool> + 'static) -> Duration {
+    let mut pool = LocalPool::new();
+    let (tx, rx) = mpsc::unbounded::<(usize, oneshot::Sender<()>)>();
+    for i in 0..MESSAGES {
+        let (ack, _) = oneshot::channel();
+        tx.unbounded_send((i, ack)).unwrap();
+    }
+    drop(tx);
+
+    let start = Instant::now();
+    pool.spawner()
+        .spawn_local(
+            rx.then(move |(i, ack)| {
+                let fut = handle(i);
+                async move {
+                    let render = fut.await;
+                    let _ = ack.send(());
+                    render
+                }
+            })
+            .for_each(|_| async {}),
+        )
+        .unwrap();
+    pool.run();
+    start.elapsed()
+}
+
+fn report(name: &str, elapsed: Duration) {
+    let rate = MESSAGES as f64 / elapsed.as_secs_f64();
+    println!("{:<12} {:>10.2?} {:>14.0} msg/s", name, elapsed, rate);
+}
+
+fn main() {
+    // warm up allocator and caches.
+    mutex_loop();
+    state_loop();
+
+    report("mutex", mutex_loop());
+    report("state", state_loop());
+}
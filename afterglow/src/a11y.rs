@@ -0,0 +1,403 @@
+//! Focus management and screen reader announcements.
+//!
+//! - `FocusTrap` keeps <kbd>Tab</kbd> inside a modal region while it's alive.
+//! - `FocusRestore` gives focus back to the element that had it when dropped, keep one in the
+//!   model of a dialog and focus returns once the container is destroyed.
+//! - `RovingTabindex` makes a list or menu a single tab stop navigated with the arrow keys.
+//! - `announce` reads a message through an ARIA live region, e.g. from `Messenger::update`.
+
+use crate::events;
+use crate::prelude::*;
+use dodrio::{RootRender, VdomWeak};
+use gloo::events::EventListenerOptions;
+use std::cell::{Cell, RefCell};
+use web_sys::{Element, HtmlElement, KeyboardEvent};
+
+const FOCUSABLE: &str = "a[href], area[href], button:not([disabled]), input:not([disabled]), \
+    select:not([disabled]), textarea:not([disabled]), iframe, [contenteditable], \
+    [tabindex]:not([tabindex=\"-1\"])";
+
+#[cfg(target_arch = "wasm32")]
+fn document() -> Option<web_sys::Document> {
+    web_sys::window()?.document()
+}
+
+/// There's no document off wasm, which makes focus handling and announcements no-ops.
+#[cfg(not(target_arch = "wasm32"))]
+fn document() -> Option<web_sys::Document> {
+    None
+}
+
+/// Focusable descendants of an element in document order.
+pub fn focusable(root: &Element) -> Vec<HtmlElement> {
+    let nodes = match root.query_selector_all(FOCUSABLE) {
+        Ok(nodes) => nodes,
+        Err(_) => return vec![],
+    };
+    (0..nodes.length())
+        .filter_map(|index| nodes.item(index))
+        .filter_map(|node| node.dyn_into::<HtmlElement>().ok())
+        .collect()
+}
+
+/// Keeps keyboard focus inside an element, released on drop.
+pub struct FocusTrap {
+    _listener: EventListener,
+}
+
+impl FocusTrap {
+    /// Focus the first focusable element of `node_ref` and cycle <kbd>Tab</kbd> within it.
+    ///
+    /// Returns `None` if the element isn't rendered yet, activate traps from `rendererd` or after
+    /// a render was acknowledged.
+    pub fn activate(node_ref: &NodeRef) -> Option<FocusTrap> {
+        let root = node_ref.get()?;
+        let document = document()?;
+        if let Some(first) = focusable(&root).first() {
+            let _ = first.focus();
+        }
+
+        let node_ref = node_ref.clone();
+        let listener = EventListener::new_with_options(
+            &document,
+            "keydown",
+            EventListenerOptions::enable_prevent_default(),
+            move |event| {
+                let event = match event.dyn_ref::<KeyboardEvent>() {
+                    Some(event) if event.key() == "Tab" => event,
+                    _ => return,
+                };
+                let root = match node_ref.get() {
+                    Some(root) => root,
+                    None => return,
+                };
+                let elements = focusable(&root);
+                let (first, last) = match (elements.first(), elements.last()) {
+                    (Some(first), Some(last)) => (first, last),
+                    _ => {
+                        event.prevent_default();
+                        return;
+                    }
+                };
+                let active = web_sys::window()
+                    .and_then(|window| window.document())
+                    .and_then(|document| document.active_element());
+                let inside = active
+                    .as_ref()
+                    .is_some_and(|active| root.contains(Some(active)));
+                let wrap_to = match active {
+                    _ if !inside => Some(first),
+                    Some(active) if event.shift_key() && active == **first => Some(last),
+                    Some(active) if !event.shift_key() && active == **last => Some(first),
+                    _ => None,
+                };
+                if let Some(element) = wrap_to {
+                    event.prevent_default();
+                    let _ = element.focus();
+                }
+            },
+        );
+        Some(FocusTrap {
+            _listener: listener,
+        })
+    }
+}
+
+/// Gives focus back to the element that had it when the guard was created.
+pub struct FocusRestore {
+    element: Option<HtmlElement>,
+}
+
+impl FocusRestore {
+    pub fn capture() -> Self {
+        let element = document()
+            .and_then(|document| document.active_element())
+            .and_then(|element| element.dyn_into::<HtmlElement>().ok());
+        FocusRestore { element }
+    }
+}
+
+impl Drop for FocusRestore {
+    fn drop(&mut self) {
+        if let Some(element) = self.element.take() {
+            if element.is_connected() {
+                let _ = element.focus();
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Orientation {
+    Horizontal,
+    Vertical,
+    /// Both arrow key pairs move, e.g. for grids of toolbar buttons.
+    Both,
+}
+
+/// A group of items where only the active one is in the tab order.
+///
+/// Render each item with `tabindex={roving.tabindex(index)}` and
+/// `data-ag-roving={roving.group()}`, and listen for `keydown` on the items with
+/// `RovingTabindex::on_keydown`.
+#[derive(Clone, Debug)]
+pub struct RovingTabindex {
+    group: &'static str,
+    active: usize,
+    len: usize,
+    orientation: Orientation,
+    wrap: bool,
+}
+
+impl RovingTabindex {
+    pub fn new(group: &'static str, len: usize) -> Self {
+        RovingTabindex {
+            group,
+            active: 0,
+            len,
+            orientation: Orientation::Vertical,
+            wrap: true,
+        }
+    }
+
+    pub fn orientation(mut self, orientation: Orientation) -> Self {
+        self.orientation = orientation;
+        self
+    }
+
+    /// Stop at the ends instead of wrapping around.
+    pub fn no_wrap(mut self) -> Self {
+        self.wrap = false;
+        self
+    }
+
+    pub fn group(&self) -> &'static str {
+        self.group
+    }
+
+    pub fn active(&self) -> usize {
+        self.active
+    }
+
+    pub fn set_len(&mut self, len: usize) {
+        self.len = len;
+        self.active = self.active.min(len.saturating_sub(1));
+    }
+
+    pub fn set_active(&mut self, index: usize) -> bool {
+        let index = index.min(self.len.saturating_sub(1));
+        std::mem::replace(&mut self.active, index) != index
+    }
+
+    pub fn tabindex(&self, index: usize) -> &'static str {
+        if index == self.active {
+            "0"
+        } else {
+            "-1"
+        }
+    }
+
+    /// The item a key moves to, `None` for keys the group doesn't handle.
+    pub fn target(&self, key: &str) -> Option<usize> {
+        if self.len == 0 {
+            return None;
+        }
+        let last = self.len - 1;
+        let (horizontal, vertical) = match self.orientation {
+            Orientation::Horizontal => (true, false),
+            Orientation::Vertical => (false, true),
+            Orientation::Both => (true, true),
+        };
+        let step = |forward: bool| match (forward, self.active) {
+            (true, active) if active < last => active + 1,
+            (true, _) if self.wrap => 0,
+            (false, 0) if self.wrap => last,
+            (false, active) => active.saturating_sub(1),
+            (true, active) => active,
+        };
+        match key {
+            "ArrowDown" if vertical => Some(step(true)),
+            "ArrowUp" if vertical => Some(step(false)),
+            "ArrowRight" if horizontal => Some(step(true)),
+            "ArrowLeft" if horizontal => Some(step(false)),
+            "Home" => Some(0),
+            "End" => Some(last),
+            _ => None,
+        }
+    }
+
+    /// Listener for `keydown` on the items, moves focus with the arrow keys.
+    pub fn on_keydown<H: 'static>(
+        &self,
+        select: fn(&mut H) -> &mut RovingTabindex,
+        sender: &MessageSender<H>,
+    ) -> impl Fn(&mut dyn RootRender, VdomWeak, Event) + 'static {
+        let roving = self.clone();
+        events::ListenerOptions::new().on(
+            move |event: KeyboardEvent| {
+                let index = roving.target(&event.key())?;
+                event.prevent_default();
+                Some(RovingMsg::focus(select, index))
+            },
+            sender,
+        )
+    }
+}
+
+/// Move the active item of a `RovingTabindex` owned by `H` and focus it.
+pub struct RovingMsg<H> {
+    select: fn(&mut H) -> &mut RovingTabindex,
+    index: usize,
+}
+
+impl<H> RovingMsg<H> {
+    pub fn focus(select: fn(&mut H) -> &mut RovingTabindex, index: usize) -> Self {
+        RovingMsg { select, index }
+    }
+}
+
+impl<H> Messenger for RovingMsg<H> {
+    type Target = H;
+
+    fn update(
+        self: Box<Self>,
+        target: &mut Self::Target,
+        _sender: &MessageSender<Self::Target>,
+        render_tx: &Sender<((), oneshot::Sender<()>)>,
+    ) -> bool {
+        let roving = (self.select)(target);
+        roving.set_active(self.index);
+        let selector = format!("[data-ag-roving=\"{}\"]", roving.group);
+        focus_rendered(render_tx, selector, roving.active as u32);
+        true
+    }
+}
+
+/// Focus the `index`th match of `selector` once `Entry` patched the new tabindex values into the
+/// document.
+#[cfg(target_arch = "wasm32")]
+fn focus_rendered(render_tx: &Sender<((), oneshot::Sender<()>)>, selector: String, index: u32) {
+    let mut render_tx = render_tx.clone();
+    spawn_local(async move {
+        let (tx, rx) = oneshot::channel();
+        let _ = render_tx.send(((), tx)).await;
+        if rx.await.is_err() {
+            return;
+        }
+        let item = document()
+            .and_then(|document| document.query_selector_all(&selector).ok())
+            .and_then(|items| items.item(index))
+            .and_then(|item| item.dyn_into::<HtmlElement>().ok());
+        if let Some(item) = item {
+            let _ = item.focus();
+        }
+    });
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn focus_rendered(_render_tx: &Sender<((), oneshot::Sender<()>)>, _selector: String, _index: u32) {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Politeness {
+    /// Read once the user is idle.
+    Polite,
+    /// Interrupt whatever is being read.
+    Assertive,
+}
+
+thread_local! {
+    static LIVE_REGIONS: RefCell<Vec<(Politeness, Element)>> = const { RefCell::new(Vec::new()) };
+    /// Bumped by every announcement, so a late one doesn't overwrite a newer message.
+    static ANNOUNCEMENTS: Cell<u32> = const { Cell::new(0) };
+}
+
+/// Screen readers skip a message that's replaced within the same turn, wait this long in between.
+const ANNOUNCE_DELAY: u32 = 100;
+
+const VISUALLY_HIDDEN: &str = "position: absolute; width: 1px; height: 1px; margin: -1px; \
+    padding: 0; overflow: hidden; clip: rect(0, 0, 0, 0); white-space: nowrap; border: 0";
+
+fn live_region(politeness: Politeness) -> Option<Element> {
+    let known = LIVE_REGIONS.with(|regions| {
+        regions
+            .borrow()
+            .iter()
+            .find(|(kind, _)| *kind == politeness)
+            .map(|(_, region)| region.clone())
+    });
+    if known.is_some() {
+        return known;
+    }
+    let document = document()?;
+    let region = document.create_element("div").ok()?;
+    let live = match politeness {
+        Politeness::Polite => "polite",
+        Politeness::Assertive => "assertive",
+    };
+    region.set_attribute("aria-live", live).ok()?;
+    region.set_attribute("aria-atomic", "true").ok()?;
+    region.set_attribute("style", VISUALLY_HIDDEN).ok()?;
+    document.body()?.append_child(&region).ok()?;
+    LIVE_REGIONS.with(|regions| regions.borrow_mut().push((politeness, region.clone())));
+    Some(region)
+}
+
+/// Have screen readers read `message`.
+pub fn announce(message: impl Into<String>, politeness: Politeness) {
+    let message = message.into();
+    if let Some(region) = live_region(politeness) {
+        // clear first so repeating the same message is announced again
+        region.set_text_content(None);
+        let announcement = ANNOUNCEMENTS.with(|count| {
+            count.set(count.get().wrapping_add(1));
+            count.get()
+        });
+        spawn_local(async move {
+            gloo::timers::future::TimeoutFuture::new(ANNOUNCE_DELAY).await;
+            if ANNOUNCEMENTS.with(Cell::get) == announcement {
+                region.set_text_content(Some(&message));
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roving_keys() {
+        let mut roving = RovingTabindex::new("menu", 3);
+        assert_eq!(roving.tabindex(0), "0");
+        assert_eq!(roving.tabindex(1), "-1");
+        assert_eq!(roving.target("ArrowUp"), Some(2));
+        assert_eq!(roving.target("ArrowRight"), None);
+        assert_eq!(roving.target("End"), Some(2));
+
+        assert!(roving.set_active(2));
+        assert!(!roving.set_active(7));
+        assert_eq!(roving.target("ArrowDown"), Some(0));
+
+        let roving = RovingTabindex::new("toolbar", 3)
+            .orientation(Orientation::Horizontal)
+            .no_wrap();
+        assert_eq!(roving.target("ArrowLeft"), Some(0));
+        assert_eq!(roving.target("ArrowRight"), Some(1));
+        assert_eq!(roving.target("ArrowDown"), None);
+    }
+
+    #[test]
+    fn focus_and_announce_without_document() {
+        let (sender, _) = futures::channel::mpsc::unbounded();
+        let (render_tx, mut renders) = futures::channel::mpsc::unbounded();
+        let mut roving = RovingTabindex::new("menu", 3);
+
+        let msg = Box::new(RovingMsg::focus(|roving| roving, 2));
+        assert!(msg.update(&mut roving, &sender, &render_tx));
+        assert_eq!(roving.active(), 2);
+        // nothing to focus, so no render is requested
+        assert!(renders.try_recv().is_err());
+
+        announce("saved", Politeness::Polite);
+    }
+}
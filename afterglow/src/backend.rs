@@ -0,0 +1,313 @@
+use crate::prelude::*;
+use crate::vnode::{VElement, VNode};
+use dodrio::builder::ElementBuilder;
+use dodrio::bumpalo::{self, Bump};
+
+/// Turns afterglow's `VNode`s into something a platform can display.
+pub trait Backend {
+    type Output;
+
+    fn build(&mut self, node: VNode) -> Self::Output;
+}
+
+/// Build dodrio nodes inside the render context's bump arena.
+pub struct Dodrio<'c, 'a> {
+    cx: &'c mut RenderContext<'a>,
+}
+
+impl<'c, 'a> Dodrio<'c, 'a> {
+    pub fn new(cx: &'c mut RenderContext<'a>) -> Self {
+        Dodrio { cx }
+    }
+
+    fn build_element(&mut self, element: VElement) -> Node<'a> {
+        let bump = self.cx.bump;
+        let mut builder = ElementBuilder::new(bump, alloc_str(bump, &element.tag));
+        if let Some(namespace) = element.namespace.as_ref() {
+            builder = builder.namespace(Some(alloc_str(bump, namespace)));
+        }
+        if let Some(key) = element.key {
+            builder = builder.key(key);
+        }
+        for (name, value) in element.attrs.iter() {
+            builder = builder.attr(alloc_str(bump, name), alloc_str(bump, value));
+        }
+        for (event, listener) in element.listeners {
+            builder = builder.on(alloc_str(bump, &event), move |_, _, event| listener(event));
+        }
+        let mut children = bumpalo::collections::Vec::new_in(bump);
+        for child in element.children {
+            self.build_into(child, &mut children);
+        }
+        builder.children(children).finish()
+    }
+
+    /// Build a node, splicing fragments and fragment components into `nodes`.
+    fn build_into(&mut self, node: VNode, nodes: &mut bumpalo::collections::Vec<'a, Node<'a>>) {
+        match node {
+            VNode::Fragment(children) => {
+                for child in children {
+                    self.build_into(child, nodes);
+                }
+            }
+            VNode::Component(component) => nodes.extend(component.dodrio_children(self.cx)),
+            node => nodes.push(self.build(node)),
+        }
+    }
+
+    /// Build the nodes a view splices into it's parent.
+    pub fn build_children(&mut self, node: VNode) -> bumpalo::collections::Vec<'a, Node<'a>> {
+        let mut nodes = bumpalo::collections::Vec::new_in(self.cx.bump);
+        self.build_into(node, &mut nodes);
+        nodes
+    }
+}
+
+impl<'c, 'a> Backend for Dodrio<'c, 'a> {
+    type Output = Node<'a>;
+
+    fn build(&mut self, node: VNode) -> Node<'a> {
+        match node {
+            VNode::Element(element) => self.build_element(element),
+            VNode::Text(content) => text(alloc_str(self.cx.bump, &content)),
+            VNode::Component(component) => component.dodrio(self.cx),
+            // dodrio needs a single root, `display: contents` keeps the wrapper out of the layout.
+            // Parents splice fragments instead through `build_children`, `Container::node` or
+            // `Container::render_children`, this is only reached for a fragment as the only root.
+            VNode::Fragment(children) => {
+                let bump = self.cx.bump;
+                let nodes = self.build_children(VNode::Fragment(children));
+                dodrio::builder::div(bump)
+                    .attr("style", "display: contents")
+                    .children(nodes)
+                    .finish()
+            }
+        }
+    }
+}
+
+fn alloc_str<'a>(bump: &'a Bump, value: &str) -> &'a str {
+    bumpalo::collections::String::from_str_in(value, bump).into_bump_str()
+}
+
+/// Serialize into an html string, listeners are dropped.
+///
+/// Useful for rendering views in tests or on a server.
+#[derive(Default)]
+pub struct Html;
+
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+
+impl Html {
+    fn write(&self, node: VNode, out: &mut String) {
+        match node {
+            VNode::Text(content) => escape_into(&content, false, out),
+            VNode::Fragment(children) => {
+                for child in children {
+                    self.write(child, out);
+                }
+            }
+            VNode::Component(component) => match component.vnode() {
+                Some(node) => self.write(node, out),
+                None => out.push_str("<template></template>"),
+            },
+            VNode::Element(element) => {
+                out.push('<');
+                out.push_str(&element.tag);
+                for (name, value) in element.attrs.iter() {
+                    out.push(' ');
+                    out.push_str(name);
+                    if !value.is_empty() {
+                        out.push_str("=\"");
+                        escape_into(value, true, out);
+                        out.push('"');
+                    }
+                }
+                out.push('>');
+
+                if VOID_ELEMENTS.contains(&&*element.tag) {
+                    return;
+                }
+                for child in element.children {
+                    self.write(child, out);
+                }
+                out.push_str("</");
+                out.push_str(&element.tag);
+                out.push('>');
+            }
+        }
+    }
+}
+
+impl Backend for Html {
+    type Output = String;
+
+    fn build(&mut self, node: VNode) -> String {
+        let mut out = String::new();
+        self.write(node, &mut out);
+        out
+    }
+}
+
+fn escape_into(value: &str, attr: bool, out: &mut String) {
+    for c in value.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' if attr => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+}
+
+/// Render a model to html, `None` if the renderer only knows how to build dodrio nodes.
+pub fn render_to_string<R>(
+    renderer: &R,
+    target: &R::Target,
+    sender: &MessageSender<R::Data>,
+) -> Option<String>
+where
+    R: Renderer + ?Sized,
+{
+    renderer.vnode(target, sender).map(|node| Html.build(node))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vnode::{self, el, View};
+
+    pub struct Counter {
+        count: i32,
+    }
+
+    impl LifeCycle for Counter {
+        fn new(_render_tx: Sender<((), oneshot::Sender<()>)>) -> Self {
+            Counter { count: 0 }
+        }
+    }
+
+    pub struct Page {
+        counter: Container<Counter>,
+    }
+
+    pub struct PageView;
+
+    impl View for PageView {
+        type Target = Page;
+        type Data = Page;
+
+        fn render(&self, target: &Self::Target, _sender: &MessageSender<Self::Data>) -> VNode {
+            el("main").child(target.counter.node()).finish()
+        }
+    }
+
+    pub enum CounterMsg {
+        Increase,
+    }
+
+    impl Messenger for CounterMsg {
+        type Target = Counter;
+
+        fn update(
+            self: Box<Self>,
+            target: &mut Self::Target,
+            _sender: &MessageSender<Self::Target>,
+            _render_tx: &Sender<((), oneshot::Sender<()>)>,
+        ) -> bool {
+            target.count += 1;
+            true
+        }
+    }
+
+    pub struct CounterView;
+
+    impl View for CounterView {
+        type Target = Counter;
+        type Data = Counter;
+
+        fn render(&self, target: &Self::Target, sender: &MessageSender<Self::Data>) -> VNode {
+            el("div")
+                .attr("class", "counter")
+                .child(vnode::text(format!("count: {}", target.count)))
+                .child(
+                    el("button")
+                        .bool_attr("disabled", target.count > 2)
+                        .listener("click", vnode::consume(|_| CounterMsg::Increase, sender))
+                        .child(vnode::text("<+1>"))
+                        .finish(),
+                )
+                .child(el("input").attr("value", "\"quoted\"").finish())
+                .finish()
+        }
+    }
+
+    #[test]
+    fn view_renders_to_html() {
+        let (sender, _) = mpsc::unbounded();
+        let html = render_to_string(&CounterView, &Counter { count: 3 }, &sender);
+        assert_eq!(
+            html.as_deref(),
+            Some(
+                "<div class=\"counter\">count: 3<button disabled>&lt;+1&gt;</button>\
+                 <input value=\"&quot;quoted&quot;\"></div>"
+            )
+        );
+    }
+
+    #[test]
+    fn embedded_container_renders_to_html() {
+        let (render_tx, _) = mpsc::unbounded();
+        let (sender, _) = mpsc::unbounded();
+        let counter = Container::new(Counter { count: 1 }, Box::new(CounterView), render_tx);
+        let html = render_to_string(&PageView, &Page { counter }, &sender).unwrap();
+        assert!(html.starts_with("<main><div class=\"counter\">count: 1<button>"));
+        assert!(html.ends_with("</div></main>"));
+    }
+
+    pub struct RowsView;
+
+    impl View for RowsView {
+        type Target = Counter;
+        type Data = Counter;
+
+        fn is_fragment(&self) -> bool {
+            true
+        }
+
+        fn render(&self, target: &Self::Target, _sender: &MessageSender<Self::Data>) -> VNode {
+            vnode::fragment((0..target.count).map(|row| {
+                el("tr")
+                    .child(el("td").child(vnode::text(row.to_string())).finish())
+                    .finish()
+            }))
+        }
+    }
+
+    pub struct TableView;
+
+    impl View for TableView {
+        type Target = Page;
+        type Data = Page;
+
+        fn render(&self, target: &Self::Target, _sender: &MessageSender<Self::Data>) -> VNode {
+            el("table").child(target.counter.node()).finish()
+        }
+    }
+
+    #[test]
+    fn fragments_render_without_wrapper() {
+        let (render_tx, _) = mpsc::unbounded();
+        let (sender, _) = mpsc::unbounded();
+        let counter = Container::new(Counter { count: 2 }, Box::new(RowsView), render_tx);
+        let html = render_to_string(&TableView, &Page { counter }, &sender);
+        assert_eq!(
+            html.as_deref(),
+            Some("<table><tr><td>0</td></tr><tr><td>1</td></tr></table>")
+        );
+    }
+}
@@ -1,7 +1,7 @@
 use crate::prelude::*;
 use futures::lock::Mutex;
 use futures::prelude::*;
-use std::rc::Rc;
+use std::rc::{Rc, Weak};
 
 pub type MsgSenders<T> = Rc<Mutex<Vec<Sender<(T, oneshot::Sender<()>)>>>>;
 /// A bus that can be subscribed to.
@@ -33,16 +33,28 @@ impl<T: Clone + 'static> BusService<T> {
         }
     }
 
-    /// Register to the bus by sending the Sender into the bus
+    /// Register to the bus by sending the Sender into the bus, for as long as the bus lives.
     pub fn register<A: 'static>(&self, remote_tx: MessageSender<A>)
     where
         T: Into<Option<Message<A>>>,
     {
+        self.subscribe(remote_tx).forget();
+    }
+
+    /// Register to the bus until the subscription is dropped.
+    pub fn subscribe<A: 'static>(&self, remote_tx: MessageSender<A>) -> Subscription
+    where
+        T: Into<Option<Message<A>>>,
+    {
+        let (tx, rx) = mpsc::unbounded::<(T, oneshot::Sender<()>)>();
         let bus = self.bus.clone();
+        let proxy_tx = tx.clone();
         spawn_local(async move {
-            let mut bus = bus.lock().await;
-            bus.mount_proxy(remote_tx);
+            let mut subs_tx = bus.lock().await.subs_tx.clone();
+            let _ = subs_tx.send(proxy_tx).await;
+            Bus::init_proxy(rx, remote_tx).await;
         });
+        Subscription::new(tx, Rc::downgrade(&self.bus))
     }
 
     /// Publish to the bus which will notify all members' sender
@@ -84,6 +96,7 @@ impl<T: Clone + 'static> Bus<T> {
     ) {
         while let Some(tx) = rx.next().await {
             let mut txs = txs.lock().await;
+            txs.retain(|tx| !tx.is_closed());
             txs.push(tx);
         }
     }
@@ -94,6 +107,7 @@ impl<T: Clone + 'static> Bus<T> {
             let txs = txs.clone();
             let msg = msg;
             async move {
+                Bus::prune(&txs).await;
                 let txs = txs.lock().await;
                 if !txs.is_empty() {
                     stream::iter(txs.iter())
@@ -129,29 +143,183 @@ impl<T: Clone + 'static> Bus<T> {
         });
     }
 
+    /// Forget senders whose proxy stopped, i.e. unsubscribed or lost it's container.
+    pub async fn prune(txs: &MsgSenders<T>) {
+        txs.lock().await.retain(|tx| !tx.is_closed());
+    }
+
     /// on behalf of the container, convert the broadcast message into a consumable form and trigger the messenger for the container
-    pub async fn init_proxy<A>(bus_rx: Receiver<(T, oneshot::Sender<()>)>, msg_tx: MessageSender<A>)
-    where
+    pub async fn init_proxy<A>(
+        mut bus_rx: Receiver<(T, oneshot::Sender<()>)>,
+        mut msg_tx: MessageSender<A>,
+    ) where
         T: Into<Option<Message<A>>>,
     {
-        bus_rx
-            .filter_map(|(msg, tx)| {
-                let msg_tx = msg_tx.clone();
-                async move {
-                    if let Some(inner_msg) = msg.into() {
-                        Some((msg_tx, inner_msg, tx))
-                    } else {
-                        let _ = tx.send(());
-                        None
-                    }
-                }
-            })
-            .for_each(|(mut msg_tx, inner_msg, tx)| async move {
+        while let Some((msg, tx)) = bus_rx.next().await {
+            if let Some(inner_msg) = msg.into() {
                 let (_tx, _rx) = oneshot::channel::<()>();
-                let _ = msg_tx.send((inner_msg, _tx)).await;
+                if msg_tx.send((inner_msg, Some(_tx))).await.is_err() {
+                    // the container is gone, closing the channel gets the sender pruned
+                    let _ = tx.send(());
+                    break;
+                }
                 let _ = _rx.await;
-                let _ = tx.send(());
-            })
-            .await;
+            }
+            let _ = tx.send(());
+        }
+    }
+}
+
+/// Keeps a container registered to a bus, dropping it unsubscribes.
+///
+/// Containers keep theirs alive by returning them from `LifeCycle::subscriptions`.
+#[must_use = "dropping a subscription unsubscribes right away"]
+pub struct Subscription(Option<Box<dyn FnOnce()>>);
+
+impl Subscription {
+    fn new<T: Clone + 'static>(
+        proxy_tx: Sender<(T, oneshot::Sender<()>)>,
+        bus: Weak<Mutex<Bus<T>>>,
+    ) -> Self {
+        Subscription(Some(Box::new(move || {
+            proxy_tx.close_channel();
+            spawn_local(async move {
+                if let Some(bus) = bus.upgrade() {
+                    let txs = bus.lock().await.txs.clone();
+                    Bus::prune(&txs).await;
+                }
+            });
+        })))
+    }
+
+    /// Stay subscribed for as long as the bus lives.
+    pub fn forget(mut self) {
+        self.0.take();
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        if let Some(unsubscribe) = self.0.take() {
+            unsubscribe();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spawner::NativeSpawner;
+    use crate::vnode::{self, View};
+
+    struct Counter {
+        count: u32,
+        bus: Option<BusService<Ping>>,
+    }
+
+    impl LifeCycle for Counter {
+        fn new(_render_tx: Sender<((), oneshot::Sender<()>)>) -> Self {
+            Counter {
+                count: 0,
+                bus: None,
+            }
+        }
+
+        fn subscriptions(&self, sender: &MessageSender<Self>) -> Vec<Subscription> {
+            let bus = self.bus.iter();
+            bus.map(|bus| bus.subscribe(sender.clone())).collect()
+        }
+    }
+
+    struct CounterView;
+
+    impl View for CounterView {
+        type Target = Counter;
+        type Data = Counter;
+
+        fn render(&self, target: &Self::Target, _sender: &MessageSender<Self::Data>) -> VNode {
+            vnode::text(target.count.to_string())
+        }
+    }
+
+    #[derive(Clone)]
+    struct Ping;
+
+    impl Messenger for Ping {
+        type Target = Counter;
+
+        fn update(
+            self: Box<Self>,
+            target: &mut Self::Target,
+            _sender: &MessageSender<Self::Target>,
+            _render_tx: &Sender<((), oneshot::Sender<()>)>,
+        ) -> bool {
+            target.count += 1;
+            false
+        }
+    }
+
+    impl From<Ping> for Option<Message<Counter>> {
+        fn from(ping: Ping) -> Self {
+            Some(Box::new(ping))
+        }
+    }
+
+    fn subscribers(service: &BusService<Ping>) -> usize {
+        let bus = service.bus.try_lock().unwrap();
+        let txs = bus.txs.try_lock().unwrap();
+        txs.len()
+    }
+
+    #[test]
+    fn dropped_subscriptions_are_removed() {
+        let spawner = NativeSpawner::current();
+        let service = BusService::<Ping>::new();
+        let (first_tx, mut first_rx) = mpsc::unbounded();
+        let (second_tx, mut second_rx) = mpsc::unbounded();
+        let first = service.subscribe(first_tx);
+        let second = service.subscribe(second_tx);
+        spawner.run_until_stalled();
+        assert_eq!(subscribers(&service), 2);
+
+        drop(first);
+        spawner.run_until_stalled();
+        assert_eq!(subscribers(&service), 1);
+
+        service.publish(Ping);
+        spawner.run_until_stalled();
+        assert!(first_rx.try_recv().is_err());
+        let (msg, ack) = second_rx.try_recv().unwrap();
+        let mut counter = Counter::new(mpsc::unbounded().0);
+        msg.update(&mut counter, &mpsc::unbounded().0, &mpsc::unbounded().0);
+        assert_eq!(counter.count, 1);
+        let _ = ack.unwrap().send(());
+
+        // a proxy whose container is gone gets pruned on the next broadcast
+        drop(second_rx);
+        service.publish(Ping);
+        spawner.run_until_stalled();
+        service.publish(Ping);
+        spawner.run_until_stalled();
+        assert_eq!(subscribers(&service), 0);
+        drop(second);
+    }
+
+    #[test]
+    fn dropped_container_unsubscribes() {
+        let spawner = NativeSpawner::current();
+        let service = BusService::<Ping>::new();
+        let (render_tx, _render_rx) = mpsc::unbounded();
+        let counter = Counter {
+            count: 0,
+            bus: Some(service.clone()),
+        };
+        let container = Container::new(counter, Box::new(CounterView), render_tx);
+        spawner.run_until_stalled();
+        assert_eq!(subscribers(&service), 1);
+
+        drop(container);
+        spawner.run_until_stalled();
+        assert_eq!(subscribers(&service), 0);
     }
 }
@@ -1,49 +1,86 @@
+use crate::head::{Head, HeadSlot};
+use crate::i18n;
+use crate::portal::Portals;
 use crate::prelude::*;
-use async_std::task;
+use crate::shortcuts::ShortcutHandle;
+use crate::style::StyleHandle;
+use crate::theme::{self, Theme};
+use crate::vnode::{Str, VNode};
 use dodrio::Vdom;
-use futures::lock::Mutex;
 use gloo::events::EventListener;
-use std::rc::Rc;
+use std::rc::{Rc, Weak};
+
+/// Frames a message waits for a borrowed model before it's dropped, in release builds.
+const BORROWED_MODEL_FRAMES: usize = 8;
 
 pub struct Container<T>
 where
     T: LifeCycle,
 {
-    pub data: Rc<Mutex<T>>,
+    pub data: Rc<State<T>>,
     pub sender: MessageSender<T>,
-    pub renderer: Render<T, T>,
+    pub renderer: SharedRender<T, T>,
     pub render_tx: Sender<((), oneshot::Sender<()>)>,
     pub handlers: Vec<EventListener>,
+    cache: Invalidator,
+    view: Rc<CachedView<T>>,
+    /// Keeps the renderer's css injected while the container lives.
+    _styles: Vec<StyleHandle>,
+    portals: Rc<Portals<T>>,
+    /// Keyboard shortcuts registered while the container lives.
+    _shortcuts: Vec<ShortcutHandle>,
+    /// Bus subscriptions, dropped right after `LifeCycle::destroyed`.
+    subscriptions: Vec<Subscription>,
+    head: Rc<HeadSlot>,
 }
 
 pub trait LifeCycle {
     fn new(render_tx: Sender<((), oneshot::Sender<()>)>) -> Self;
     fn mounted(
-        sender: &MessageSender<Self>,
-        render_tx: &Sender<((), oneshot::Sender<()>)>,
-        handlers: &mut Vec<EventListener>,
+        _sender: &MessageSender<Self>,
+        _render_tx: &Sender<((), oneshot::Sender<()>)>,
+        _handlers: &mut Vec<EventListener>,
     ) {
     }
 
+    /// Keyboard shortcuts kept registered until the container is dropped.
+    fn shortcuts(_sender: &MessageSender<Self>) -> Vec<ShortcutHandle>
+    where
+        Self: Sized,
+    {
+        vec![]
+    }
+
+    /// Bus subscriptions kept until the container is dropped, e.g. `bus.subscribe(sender.clone())`.
+    fn subscriptions(&self, _sender: &MessageSender<Self>) -> Vec<Subscription>
+    where
+        Self: Sized,
+    {
+        vec![]
+    }
+
+    /// Title, meta tags and links for the document while the container is mounted, see `Head`.
+    fn head(&self) -> Option<Head> {
+        None
+    }
+
     fn destroyed(
         &self,
-        sender: &MessageSender<Self>,
-        render_tx: &Sender<((), oneshot::Sender<()>)>,
+        _sender: &MessageSender<Self>,
+        _render_tx: &Sender<((), oneshot::Sender<()>)>,
     ) {
     }
 
     fn rendererd(
         &self,
-        sender: MessageSender<Self>,
-        render_tx: &Sender<((), oneshot::Sender<()>)>,
+        _sender: MessageSender<Self>,
+        _render_tx: &Sender<((), oneshot::Sender<()>)>,
     ) {
     }
 }
 
 impl LifeCycle for () {
-    fn new(render_tx: Sender<((), oneshot::Sender<()>)>) -> Self {
-        ()
-    }
+    fn new(_render_tx: Sender<((), oneshot::Sender<()>)>) -> Self {}
 }
 
 impl<T> Drop for Container<T>
@@ -51,9 +88,17 @@ where
     T: LifeCycle,
 {
     fn drop(&mut self) {
-        if let Some(data) = self.data.try_lock().as_ref() {
-            data.destroyed(&self.sender, &self.render_tx);
-        }
+        let _ = self
+            .data
+            .render(|data| data.destroyed(&self.sender, &self.render_tx));
+        self.subscriptions.clear();
+    }
+}
+
+/// Hand the model's head to the registry, unless the container is gone already.
+fn sync_head<T: LifeCycle>(data: &State<T>, slot: &Weak<HeadSlot>) {
+    if let (Some(slot), Ok(head)) = (slot.upgrade(), data.render(|data| data.head())) {
+        slot.set(head);
     }
 }
 
@@ -70,7 +115,7 @@ where
     {
         let renderer = Box::new(R::default());
         let data = T::new(render_tx.clone());
-        let (sender, receiver) = mpsc::unbounded::<(Message<T>, oneshot::Sender<()>)>();
+        let (_sender, receiver) = mpsc::unbounded::<(Message<T>, Ack)>();
         let mut container = Container::new(data, renderer, render_tx);
         <T as LifeCycle>::mounted(
             &container.sender,
@@ -90,13 +135,50 @@ where
     where
         T: 'static,
     {
-        let (sender, receiver) = mpsc::unbounded::<(Message<T>, oneshot::Sender<()>)>();
+        let (sender, receiver) = mpsc::unbounded::<(Message<T>, Ack)>();
+        let data = Rc::new(State::new(data));
+        let renderer: SharedRender<T, T> = Rc::from(renderer);
+        let cache = Invalidator::default();
+        let styles = renderer.styles();
+        let styles = styles
+            .into_iter()
+            .map(|(renderer, style)| style.attach(renderer))
+            .collect();
+        renderer.mounted(&sender);
+        let portals = Rc::new(Portals::new(
+            renderer.portals(),
+            data.clone(),
+            sender.clone(),
+            cache.clone(),
+        ));
+        let view = Rc::new(CachedView::new(
+            ContainerView::new(
+                data.clone(),
+                renderer.clone(),
+                sender.clone(),
+                cache.clone(),
+            ),
+            cache.clone(),
+            Rc::downgrade(&portals),
+        ));
+        let shortcuts = T::shortcuts(&sender);
+        let subscriptions = data
+            .render(|data| data.subscriptions(&sender))
+            .unwrap_or_default();
+        let head = Rc::new(HeadSlot::new(cache.clone()));
         let mut container = Container {
-            data: Rc::new(Mutex::new(data)),
+            data,
             sender,
             renderer,
             render_tx,
             handlers: vec![],
+            cache,
+            view,
+            _styles: styles,
+            portals,
+            _shortcuts: shortcuts,
+            subscriptions,
+            head,
         };
         <T as LifeCycle>::mounted(
             &container.sender,
@@ -116,13 +198,17 @@ where
         let render_tx_handle = self.render_tx.clone();
         let tx_handle = tx.clone();
         let sender = self.sender.clone();
+        let cache = self.cache.clone();
+        let portals = Rc::downgrade(&self.portals);
+        let head = Rc::downgrade(&self.head);
         let fut = async move {
             {
                 let (tx, rx) = oneshot::channel();
                 let _ = render_tx_handle.clone().send(((), tx)).await;
                 let _ = rx.await;
-                let data = data_handle.lock().await;
-                data.rendererd(sender.clone(), &render_tx_handle);
+                let _ =
+                    data_handle.render(|data| data.rendererd(sender.clone(), &render_tx_handle));
+                sync_head(&data_handle, &head);
             }
 
             rx.then(|(msg, inner_tx)| {
@@ -130,20 +216,62 @@ where
                 let tx = tx_handle.clone();
                 let render_tx = render_tx_handle.clone();
                 async move {
-                    let mut data_inner = data.lock().await;
-                    let should_render = msg.update(&mut *data_inner, &tx, &render_tx);
-                    let _ = inner_tx.send(());
+                    let mut msg = Some(msg);
+                    let mut frames = 0;
+                    let should_render = loop {
+                        let update = data.update(|data| {
+                            msg.take()
+                                .is_some_and(|msg| msg.update(data, &tx, &render_tx))
+                        });
+                        let err = match update {
+                            Ok(render) => break render,
+                            Err(err) => err,
+                        };
+                        // the model is still borrowed, e.g. across an await
+                        if cfg!(debug_assertions) {
+                            panic!("message sent while the model is borrowed: {}", err);
+                        }
+                        if frames == BORROWED_MODEL_FRAMES {
+                            log::error!("dropped message after {} frames: {}", frames, err);
+                            break false;
+                        }
+                        if frames == 0 {
+                            log::warn!("delaying message: {}", err);
+                        }
+                        frames += 1;
+                        let (frame_tx, frame) = oneshot::channel();
+                        if render_tx.clone().send(((), frame_tx)).await.is_err()
+                            || frame.await.is_err()
+                        {
+                            log::error!("dropped message: {}", err);
+                            break false;
+                        }
+                    };
+                    if let Some(inner_tx) = inner_tx {
+                        let _ = inner_tx.send(());
+                    }
                     (should_render, render_tx.clone())
                 }
             })
-            .filter_map(|(render, render_tx)| async move {
-                if render {
-                    Some(render_tx)
-                } else {
-                    None
+            .filter_map(|(render, render_tx)| {
+                let data = data_handle.clone();
+                let head = head.clone();
+                let cache = cache.clone();
+                let portals = portals.upgrade();
+                async move {
+                    if render {
+                        sync_head(&data, &head);
+                        cache.invalidate();
+                        if let Some(portals) = portals {
+                            portals.sync();
+                        }
+                        Some(render_tx)
+                    } else {
+                        None
+                    }
                 }
             })
-            .for_each_concurrent(std::usize::MAX, |mut render_tx| async move {
+            .for_each_concurrent(usize::MAX, |mut render_tx| async move {
                 let (tx, rx) = oneshot::channel();
                 let _ = render_tx.send(((), tx)).await;
                 let _ = rx.await;
@@ -153,14 +281,52 @@ where
         spawn_local(fut);
     }
 
-    /// Provide current view, return template node if currently locked.
-    pub fn render<'a>(&self, ctx: &mut RenderContext<'a>) -> Node<'a> {
-        let bump = ctx.bump;
-        if let Some(data) = self.data.try_lock() {
-            self.renderer.view(&*data, ctx, &self.sender)
-        } else {
-            dodrio::builder::template(bump).finish()
-        }
+    /// Keep a bus subscription made after creation until the container is dropped.
+    pub fn subscribe(&mut self, subscription: Subscription) {
+        self.subscriptions.push(subscription);
+    }
+
+    /// Provide current view, return template node if the model is being updated.
+    ///
+    /// The previous subtree is reused as long as no message reported a change since the last frame.
+    /// A fragment is wrapped in a `display: contents` div here, which isn't valid inside tables or
+    /// lists, use `render_children` where the parent can take several nodes.
+    pub fn render<'a>(&self, ctx: &mut RenderContext<'a>) -> Node<'a>
+    where
+        T: 'static,
+    {
+        self.view.render(ctx)
+    }
+
+    /// Nodes to splice into the parent's children, several if the renderer returns a fragment.
+    ///
+    /// See `Renderer::view_children`.
+    pub fn render_children<'a>(
+        &self,
+        ctx: &mut RenderContext<'a>,
+    ) -> dodrio::bumpalo::collections::Vec<'a, Node<'a>>
+    where
+        T: 'static,
+    {
+        self.view.render_children(ctx)
+    }
+
+    /// Embed the container into a backend independent view.
+    pub fn node(&self) -> VNode
+    where
+        T: 'static,
+    {
+        VNode::Component(self.view.clone())
+    }
+
+    /// Drop the cached view, for renderers that read state outside of the container's model.
+    pub fn invalidate(&self) {
+        self.cache.invalidate();
+    }
+
+    /// Get a handle that can invalidate the cached view later on.
+    pub fn invalidator(&self) -> Invalidator {
+        self.cache.clone()
     }
 }
 
@@ -173,6 +339,14 @@ pub struct Entry {
 
 pub enum EntryMessage {
     Render,
+    /// Render, then acknowledge once dodrio patched the document.
+    Rendered(oneshot::Sender<()>),
+    /// Switch to another theme and rerender everything that depends on it.
+    SetTheme(Box<Theme>),
+    /// Switch to another locale and rerender everything that was translated.
+    SetLocale(Str),
+    /// Add or replace a catalog and rerender everything that was translated.
+    AddCatalog(Box<Catalog>),
     Eject(oneshot::Sender<()>),
 }
 
@@ -195,55 +369,96 @@ impl Entry {
         Entry::default()
     }
 
+    /// Queue a locale switch, see `EntryMessage::SetLocale`.
+    pub fn set_locale(&self, locale: impl Into<Str>) {
+        let _ = self
+            .msg_tx
+            .unbounded_send(EntryMessage::SetLocale(locale.into()));
+    }
+
+    /// Queue a new catalog, see `EntryMessage::AddCatalog`.
+    pub fn add_catalog(&self, catalog: Catalog) {
+        let _ = self
+            .msg_tx
+            .unbounded_send(EntryMessage::AddCatalog(Box::new(catalog)));
+    }
+
+    /// Queue a theme switch, see `EntryMessage::SetTheme`.
+    pub fn set_theme(&self, theme: Theme) {
+        let _ = self
+            .msg_tx
+            .unbounded_send(EntryMessage::SetTheme(Box::new(theme)));
+    }
+
     fn handle_message<T: LifeCycle + 'static>(
         &mut self,
         data: T,
         block: &web_sys::HtmlElement,
         renderer: Render<T, T>,
-    ) -> task::JoinHandle<()> {
+    ) -> JoinHandle<()> {
         let rx = self.msg_rx.take().unwrap();
         let render_tx = self.render_tx.clone();
         let root_container = Container::new(data, renderer, render_tx);
-        let vdom = Vdom::new(&block, root_container);
+        let vdom = Vdom::new(block, root_container);
+        theme::add_root(block);
+        let root = block.clone();
 
-        task::spawn_local(async move {
+        spawn(async move {
             log::trace!("start handling entry");
 
             let vdom = vdom;
             let weak = vdom.weak().clone();
 
+            // `Some(ack)` for every message asking for a render, `None` ends the loop
             rx.then(|msg| async move {
                 match msg {
                     EntryMessage::Eject(tx) => {
                         let _ = tx.send(());
                         None
                     }
-                    x => Some(x),
+                    EntryMessage::SetTheme(theme) => {
+                        theme::set_theme(*theme);
+                        Some(None)
+                    }
+                    EntryMessage::SetLocale(locale) => {
+                        i18n::set_locale(locale);
+                        Some(None)
+                    }
+                    EntryMessage::AddCatalog(catalog) => {
+                        i18n::add_catalog(*catalog);
+                        Some(None)
+                    }
+                    EntryMessage::Render => Some(None),
+                    EntryMessage::Rendered(ack) => Some(Some(ack)),
                 }
             })
             .take_while(|msg| {
                 let rv = msg.is_some();
                 async move { rv }
             })
-            .for_each(|_| async {
+            .for_each(|ack| async {
                 weak.render().await.expect("unable to rerender");
+                if let Some(Some(ack)) = ack {
+                    let _ = ack.send(());
+                }
             })
             .await;
+            theme::remove_root(&root);
             log::trace!("ejected");
         })
     }
 
-    fn handle_render(&mut self) -> task::JoinHandle<()> {
+    fn handle_render(&mut self) -> JoinHandle<()> {
         let render_rx = self.render_rx.take().unwrap();
         let msg_tx = self.msg_tx.clone();
 
-        task::spawn_local(async move {
+        spawn(async move {
             log::trace!("start handling for rendering");
             render_rx
-                .for_each(|_| {
+                .for_each(|(_, ack)| {
                     let mut msg_tx = msg_tx.clone();
                     async move {
-                        let _ = msg_tx.send(EntryMessage::Render).await;
+                        let _ = msg_tx.send(EntryMessage::Rendered(ack)).await;
                     }
                 })
                 .await;
@@ -275,8 +490,7 @@ impl Entry {
     ) -> Self {
         let mut entry = Entry::new();
         let doc = web_sys::window()
-            .map(|win| win.document())
-            .flatten()
+            .and_then(|win| win.document())
             .expect("unable to find document");
 
         let block = id
@@ -312,6 +526,7 @@ impl Entry {
 }
 
 #[cfg(test)]
+#[allow(dead_code)]
 pub mod tests {
     use super::*;
 
@@ -321,7 +536,7 @@ pub mod tests {
     }
 
     impl LifeCycle for Model {
-        fn new(render_tx: Sender<((), oneshot::Sender<()>)>) -> Self {
+        fn new(_render_tx: Sender<((), oneshot::Sender<()>)>) -> Self {
             Model {
                 status: true,
                 embed: None,
@@ -330,11 +545,11 @@ pub mod tests {
 
         fn mounted(
             sender: &MessageSender<Self>,
-            render_tx: &Sender<((), oneshot::Sender<()>)>,
-            handlers: &mut Vec<EventListener>,
+            _render_tx: &Sender<((), oneshot::Sender<()>)>,
+            _handlers: &mut Vec<EventListener>,
         ) {
-            let handle1 = ClickEvents::Clicked.dispatch(&sender);
-            let handle2 = ClickEvents::Clicked.dispatch(&sender);
+            let _handle1 = ClickEvents::Clicked.dispatch(sender);
+            let _handle2 = ClickEvents::Clicked.dispatch(sender);
             // let combined = join(handle1, handle2);
 
             // spawn_local(async {
@@ -353,8 +568,8 @@ pub mod tests {
         fn update(
             self: Box<Self>,
             target: &mut Self::Target,
-            sender: &MessageSender<Self::Target>,
-            render_tx: &Sender<((), oneshot::Sender<()>)>,
+            _sender: &MessageSender<Self::Target>,
+            _render_tx: &Sender<((), oneshot::Sender<()>)>,
         ) -> bool {
             match *self {
                 ClickEvents::Clicked => {
@@ -392,7 +607,7 @@ pub mod tests {
                     dodrio::builder::div(bump)
                         .attr("class", "button")
                         .child(text(value))
-                        .on("click", consume(|e| ClickEvents::Clicked, &sender))
+                        .on("click", consume(|_e| ClickEvents::Clicked, sender))
                         .finish(),
                 ])
                 .finish()
@@ -430,7 +645,7 @@ pub mod tests {
                             text(value),
                             dodrio::builder::div(bump)
                                 .attr("class", "button")
-                                .on("click", consume(|e| ClickEvents::Clicked, &sender))
+                                .on("click", consume(|_e| ClickEvents::Clicked, sender))
                                 .finish(),
                         ])
                         .finish(),
@@ -450,23 +665,19 @@ pub mod tests {
             sender: &MessageSender<Self::Data>,
         ) -> Node<'a> {
             let bump = ctx.bump;
-            let card_view = RenderAsCard.view(target, ctx, &sender);
-            let box_view = RenderAsBox.view(target, ctx, &sender);
-            let embed_view = target
-                .embed
-                .as_ref()
-                .map(|embed| {
-                    embed
-                        .data
-                        .try_lock()
-                        .map(|model| RenderAsBox.view(&model, ctx, &embed.sender))
-                })
-                .flatten();
+            let card_view = RenderAsCard.view(target, ctx, sender);
+            let box_view = RenderAsBox.view(target, ctx, sender);
+            let embed_view = target.embed.as_ref().and_then(|embed| {
+                embed
+                    .data
+                    .render(|model| RenderAsBox.view(model, ctx, &embed.sender))
+                    .ok()
+            });
 
             let mut child_nodes = vec![];
             child_nodes = child_nodes
                 .into_iter()
-                .chain(embed_view.into_iter())
+                .chain(embed_view)
                 .collect::<Vec<_>>();
             child_nodes.push(
                 dodrio::builder::link(bump)
@@ -487,14 +698,13 @@ pub mod tests {
     }
 
     pub fn setup() {
-        let embed_data = Model {
+        let _embed_data = Model {
             status: false,
             embed: None,
         };
 
         let block: web_sys::HtmlElement = web_sys::window()
-            .map(|win| win.document())
-            .flatten()
+            .and_then(|win| win.document())
             .map(|doc| doc.body().unwrap())
             .unwrap();
 
@@ -512,4 +722,84 @@ pub mod tests {
         log::info!("start testing");
         setup();
     }
+
+    #[test]
+    pub fn test_native_message_loop() {
+        let spawner = crate::spawner::NativeSpawner::current();
+        let (render_tx, render_rx) = mpsc::unbounded::<((), oneshot::Sender<()>)>();
+        spawn_local(render_rx.for_each(|(_, ack)| async move {
+            let _ = ack.send(());
+        }));
+
+        let model = Model {
+            status: true,
+            embed: None,
+        };
+        // mounted dispatches two clicks on it's own.
+        let container = Container::new(model, Box::new(RenderAsBox), render_tx);
+        ClickEvents::Clicked.send(&container.sender);
+        spawner.run_until_stalled();
+
+        assert_eq!(container.data.render(|model| model.status), Ok(false));
+    }
+    #[test]
+    #[cfg_attr(debug_assertions, should_panic(expected = "model is borrowed"))]
+    pub fn messages_wait_for_borrowed_model() {
+        let spawner = crate::spawner::NativeSpawner::current();
+        let (render_tx, mut render_rx) = mpsc::unbounded::<((), oneshot::Sender<()>)>();
+        let container = Container::new(
+            Model::new(render_tx.clone()),
+            Box::new(RenderAsBox),
+            render_tx,
+        );
+        ClickEvents::Clicked.send(&container.sender);
+
+        let borrowed = container.data.try_borrow();
+        for _ in 0..3 {
+            spawner.run_until_stalled();
+            let (_, ack) = render_rx.try_recv().unwrap();
+            let _ = ack.send(());
+        }
+        assert!(borrowed.as_ref().is_some_and(|model| model.status));
+        drop(borrowed);
+
+        spawner.run_until_stalled();
+        while let Ok((_, ack)) = render_rx.try_recv() {
+            let _ = ack.send(());
+            spawner.run_until_stalled();
+        }
+        // two clicks from `mounted` and the one sent above
+        assert_eq!(container.data.render(|model| model.status), Ok(false));
+    }
+
+    #[test]
+    #[cfg(not(debug_assertions))]
+    pub fn messages_to_borrowed_model_are_dropped() {
+        let spawner = crate::spawner::NativeSpawner::current();
+        let (render_tx, mut render_rx) = mpsc::unbounded::<((), oneshot::Sender<()>)>();
+        let container = Container::new(
+            Model::new(render_tx.clone()),
+            Box::new(RenderAsBox),
+            render_tx,
+        );
+        spawner.run_until_stalled();
+        while let Ok((_, ack)) = render_rx.try_recv() {
+            let _ = ack.send(());
+            spawner.run_until_stalled();
+        }
+        let before = container.data.render(|model| model.status);
+
+        ClickEvents::Clicked.send(&container.sender);
+        let borrowed = container.data.try_borrow();
+        spawner.run_until_stalled();
+        // gives up after a bounded number of frames
+        while let Ok((_, ack)) = render_rx.try_recv() {
+            let _ = ack.send(());
+            spawner.run_until_stalled();
+        }
+        drop(borrowed);
+        spawner.run_until_stalled();
+        assert!(render_rx.try_recv().is_err());
+        assert_eq!(container.data.render(|model| model.status), before);
+    }
 }
@@ -0,0 +1,299 @@
+//! Typed helpers for turning DOM events into messages.
+//!
+//! The free functions cover the common cases, `ListenerOptions` exposes the same helpers with
+//! `stop_propagation` and `prevent_default` applied. `passive` only affects `listen`.
+
+use crate::prelude::*;
+use dodrio::{RootRender, VdomWeak};
+use gloo::events::EventListenerOptions;
+use web_sys::{EventTarget, HtmlInputElement, HtmlSelectElement, HtmlTextAreaElement};
+
+pub use web_sys::{KeyboardEvent, MouseEvent};
+
+/// How a listener treats the event before converting it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ListenerOptions {
+    pub prevent_default: bool,
+    pub stop_propagation: bool,
+    /// A passive listener promises not to call `prevent_default`, which lets the browser start
+    /// scrolling right away, so `prevent_default` is skipped. Only listeners attached with `listen`
+    /// can be passive, dodrio adds the listeners of rendered nodes itself and the flag is ignored
+    /// for them.
+    pub passive: bool,
+}
+
+impl ListenerOptions {
+    pub fn new() -> Self {
+        ListenerOptions::default()
+    }
+
+    pub fn prevent_default(mut self) -> Self {
+        self.prevent_default = true;
+        self
+    }
+
+    pub fn stop_propagation(mut self) -> Self {
+        self.stop_propagation = true;
+        self
+    }
+
+    /// Register the listener as passive, see `ListenerOptions::passive`.
+    pub fn passive(mut self) -> Self {
+        self.passive = true;
+        self
+    }
+
+    /// Whether `prevent_default` is called, a passive listener can't.
+    fn prevents_default(&self, passive_listener: bool) -> bool {
+        self.prevent_default && !passive_listener
+    }
+
+    fn apply(&self, event: &Event, passive_listener: bool) {
+        if self.prevents_default(passive_listener) {
+            event.prevent_default();
+        }
+        if self.stop_propagation {
+            event.stop_propagation();
+        }
+    }
+
+    /// Cast the event into `E` and convert it, events that fail to cast or convert into `None`
+    /// are ignored.
+    pub fn on<E, T, M>(
+        self,
+        convert: impl Fn(E) -> Option<M> + 'static,
+        sender: &MessageSender<T>,
+    ) -> impl Fn(&mut dyn RootRender, VdomWeak, Event) + 'static
+    where
+        E: JsCast,
+        M: Messenger<Target = T> + 'static,
+        T: 'static,
+    {
+        let sender = sender.clone();
+        move |_, _, event| {
+            // dodrio never registers passive listeners
+            self.apply(&event, false);
+            if let Some(msg) = event.dyn_into::<E>().ok().and_then(&convert) {
+                msg.send(&sender);
+            }
+        }
+    }
+
+    /// Convert the current value of an `<input>`, `<textarea>` or `<select>` on every input.
+    pub fn on_input<T, M>(
+        self,
+        convert: impl Fn(String) -> M + 'static,
+        sender: &MessageSender<T>,
+    ) -> impl Fn(&mut dyn RootRender, VdomWeak, Event) + 'static
+    where
+        M: Messenger<Target = T> + 'static,
+        T: 'static,
+    {
+        self.on(
+            move |event: Event| target_value(&event).map(&convert),
+            sender,
+        )
+    }
+
+    /// Same as `on_input`, triggered once the value is committed.
+    pub fn on_change<T, M>(
+        self,
+        convert: impl Fn(String) -> M + 'static,
+        sender: &MessageSender<T>,
+    ) -> impl Fn(&mut dyn RootRender, VdomWeak, Event) + 'static
+    where
+        M: Messenger<Target = T> + 'static,
+        T: 'static,
+    {
+        self.on_input(convert, sender)
+    }
+
+    /// Convert the checked state of a checkbox or radio input.
+    pub fn on_change_checked<T, M>(
+        self,
+        convert: impl Fn(bool) -> M + 'static,
+        sender: &MessageSender<T>,
+    ) -> impl Fn(&mut dyn RootRender, VdomWeak, Event) + 'static
+    where
+        M: Messenger<Target = T> + 'static,
+        T: 'static,
+    {
+        self.on(
+            move |event: Event| {
+                event
+                    .target()
+                    .and_then(|target| target.dyn_into::<HtmlInputElement>().ok())
+                    .map(|input| convert(input.checked()))
+            },
+            sender,
+        )
+    }
+
+    pub fn on_keydown<T, M>(
+        self,
+        convert: impl Fn(KeyboardEvent) -> Option<M> + 'static,
+        sender: &MessageSender<T>,
+    ) -> impl Fn(&mut dyn RootRender, VdomWeak, Event) + 'static
+    where
+        M: Messenger<Target = T> + 'static,
+        T: 'static,
+    {
+        self.on(convert, sender)
+    }
+
+    /// Submit a form without letting the browser navigate away, even with `passive` set.
+    pub fn on_submit<T, M>(
+        self,
+        convert: impl Fn() -> M + 'static,
+        sender: &MessageSender<T>,
+    ) -> impl Fn(&mut dyn RootRender, VdomWeak, Event) + 'static
+    where
+        M: Messenger<Target = T> + 'static,
+        T: 'static,
+    {
+        self.prevent_default()
+            .on(move |_: Event| Some(convert()), sender)
+    }
+
+    /// Attach a listener outside of the vdom, e.g. to `window` inside `LifeCycle::mounted`.
+    ///
+    /// Keep the returned listener alive for as long as the events are wanted, usually by pushing
+    /// it into the container's handlers.
+    pub fn listen<E, T, M>(
+        self,
+        target: &EventTarget,
+        event_type: &'static str,
+        convert: impl Fn(E) -> Option<M> + 'static,
+        sender: &MessageSender<T>,
+    ) -> EventListener
+    where
+        E: JsCast,
+        M: Messenger<Target = T> + 'static,
+        T: 'static,
+    {
+        let sender = sender.clone();
+        let options = if self.passive {
+            EventListenerOptions::default()
+        } else {
+            EventListenerOptions::enable_prevent_default()
+        };
+        EventListener::new_with_options(target, event_type, options, move |event| {
+            self.apply(event, self.passive);
+            if let Some(msg) = event.clone().dyn_into::<E>().ok().and_then(&convert) {
+                msg.send(&sender);
+            }
+        })
+    }
+}
+
+/// Read the value of the element an event was dispatched on.
+pub fn target_value(event: &Event) -> Option<String> {
+    let target = event.target()?;
+    if let Some(input) = target.dyn_ref::<HtmlInputElement>() {
+        Some(input.value())
+    } else if let Some(area) = target.dyn_ref::<HtmlTextAreaElement>() {
+        Some(area.value())
+    } else {
+        target
+            .dyn_ref::<HtmlSelectElement>()
+            .map(|select| select.value())
+    }
+}
+
+/// Cast the event into `E` and convert it, see `ListenerOptions::on`.
+pub fn on_event<E, T, M>(
+    convert: impl Fn(E) -> Option<M> + 'static,
+    sender: &MessageSender<T>,
+) -> impl Fn(&mut dyn RootRender, VdomWeak, Event) + 'static
+where
+    E: JsCast,
+    M: Messenger<Target = T> + 'static,
+    T: 'static,
+{
+    ListenerOptions::new().on(convert, sender)
+}
+
+pub fn on_input<T, M>(
+    convert: impl Fn(String) -> M + 'static,
+    sender: &MessageSender<T>,
+) -> impl Fn(&mut dyn RootRender, VdomWeak, Event) + 'static
+where
+    M: Messenger<Target = T> + 'static,
+    T: 'static,
+{
+    ListenerOptions::new().on_input(convert, sender)
+}
+
+pub fn on_change<T, M>(
+    convert: impl Fn(String) -> M + 'static,
+    sender: &MessageSender<T>,
+) -> impl Fn(&mut dyn RootRender, VdomWeak, Event) + 'static
+where
+    M: Messenger<Target = T> + 'static,
+    T: 'static,
+{
+    ListenerOptions::new().on_change(convert, sender)
+}
+
+pub fn on_change_checked<T, M>(
+    convert: impl Fn(bool) -> M + 'static,
+    sender: &MessageSender<T>,
+) -> impl Fn(&mut dyn RootRender, VdomWeak, Event) + 'static
+where
+    M: Messenger<Target = T> + 'static,
+    T: 'static,
+{
+    ListenerOptions::new().on_change_checked(convert, sender)
+}
+
+pub fn on_keydown<T, M>(
+    convert: impl Fn(KeyboardEvent) -> Option<M> + 'static,
+    sender: &MessageSender<T>,
+) -> impl Fn(&mut dyn RootRender, VdomWeak, Event) + 'static
+where
+    M: Messenger<Target = T> + 'static,
+    T: 'static,
+{
+    ListenerOptions::new().on_keydown(convert, sender)
+}
+
+pub fn on_submit<T, M>(
+    convert: impl Fn() -> M + 'static,
+    sender: &MessageSender<T>,
+) -> impl Fn(&mut dyn RootRender, VdomWeak, Event) + 'static
+where
+    M: Messenger<Target = T> + 'static,
+    T: 'static,
+{
+    ListenerOptions::new().on_submit(convert, sender)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[test]
+    fn options_builder() {
+        let options = ListenerOptions::new().stop_propagation().passive();
+        assert!(options.stop_propagation && options.passive);
+        assert!(!options.prevent_default);
+    }
+
+    #[test]
+    fn passive_only_affects_passive_listeners() {
+        let options = ListenerOptions::new().prevent_default().passive();
+        assert!(options.prevents_default(false));
+        assert!(!options.prevents_default(true));
+    }
+
+    #[wasm_bindgen_test]
+    fn reads_input_value() {
+        let document = web_sys::window().unwrap().document().unwrap();
+        let input: HtmlInputElement = document.create_element("input").unwrap().unchecked_into();
+        input.set_value("afterglow");
+        let event = Event::new("input").unwrap();
+        input.dispatch_event(&event).unwrap();
+        assert_eq!(target_value(&event).as_deref(), Some("afterglow"));
+    }
+}
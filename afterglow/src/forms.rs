@@ -0,0 +1,625 @@
+//! Two-way bindings between form controls and the fields of a struct.
+//!
+//! A model keeps a `Form<T>` around and implements `FormHost<T>`, the renderer then builds
+//! controls with `Form::input`, `Form::textarea`, `Form::select` and `Form::checkbox`. Every
+//! control sends a `FormMsg` back to the model, so no per-field message variants are needed.
+//!
+//! ```ignore
+//! const NAME: Field<Person> = Field::new("name", |p| p.name.clone(), |p, v| p.name = v);
+//!
+//! html!(bump, <form listen:submit={target.form.on_submit(sender)}>
+//!     { target.form.input(bump, NAME, sender).attr("class", "name").finish() }
+//!     <button type="submit" disabled={target.form.submitting()}>"Save"</button>
+//! </form>)
+//! ```
+
+pub mod validation;
+
+pub use validation::{length, pattern, required, Rule, RuleResult, ValidateOn};
+
+use crate::events;
+use crate::prelude::*;
+use dodrio::builder::{self, ElementBuilder};
+use dodrio::bumpalo::{self, Bump};
+use dodrio::{Attribute, Listener, RootRender, VdomWeak};
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use validation::Validation;
+
+pub type ControlBuilder<'a> = ElementBuilder<
+    'a,
+    bumpalo::collections::Vec<'a, Listener<'a>>,
+    bumpalo::collections::Vec<'a, Attribute<'a>>,
+    bumpalo::collections::Vec<'a, Node<'a>>,
+>;
+
+/// A named field of `T` holding a `V`, `String` for text controls and `bool` for checkboxes.
+pub struct Field<T, V = String> {
+    pub name: &'static str,
+    get: fn(&T) -> V,
+    set: fn(&mut T, V),
+}
+
+impl<T, V> Field<T, V> {
+    pub const fn new(name: &'static str, get: fn(&T) -> V, set: fn(&mut T, V)) -> Self {
+        Field { name, get, set }
+    }
+
+    pub fn get(&self, value: &T) -> V {
+        (self.get)(value)
+    }
+}
+
+impl<T, V> Clone for Field<T, V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T, V> Copy for Field<T, V> {}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FieldState {
+    /// The value differs from the one the form started with.
+    pub dirty: bool,
+    /// The control lost focus at least once.
+    pub touched: bool,
+}
+
+/// Values of a form together with the state of it's fields and submission.
+pub struct Form<T> {
+    value: T,
+    initial: T,
+    fields: HashMap<&'static str, FieldState>,
+    submitting: bool,
+    submit_count: u32,
+    validation: Validation<T>,
+}
+
+impl<T: Clone> Form<T> {
+    pub fn new(value: T) -> Self {
+        Form {
+            initial: value.clone(),
+            value,
+            fields: HashMap::new(),
+            submitting: false,
+            submit_count: 0,
+            validation: Validation::default(),
+        }
+    }
+
+    /// Drop every edit and go back to the initial values.
+    pub fn reset(&mut self) {
+        self.value = self.initial.clone();
+        self.fields.clear();
+        self.submitting = false;
+        self.validation.clear();
+    }
+
+    /// Accept the current values as the new initial ones, e.g. after they were saved.
+    pub fn commit(&mut self) {
+        self.initial = self.value.clone();
+        for state in self.fields.values_mut() {
+            state.dirty = false;
+        }
+    }
+}
+
+impl<T> Form<T> {
+    pub fn value(&self) -> &T {
+        &self.value
+    }
+
+    pub fn into_value(self) -> T {
+        self.value
+    }
+
+    pub fn set<V: PartialEq>(&mut self, field: Field<T, V>, value: V) {
+        let dirty = value != field.get(&self.initial);
+        (field.set)(&mut self.value, value);
+        self.fields.entry(field.name).or_default().dirty = dirty;
+    }
+
+    /// Mark a field as touched, returns false if it already was.
+    pub fn touch(&mut self, name: &'static str) -> bool {
+        let state = self.fields.entry(name).or_default();
+        !std::mem::replace(&mut state.touched, true)
+    }
+
+    pub fn field(&self, name: &str) -> FieldState {
+        self.fields.get(name).copied().unwrap_or_default()
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.fields.values().any(|state| state.dirty)
+    }
+
+    pub fn submitting(&self) -> bool {
+        self.submitting
+    }
+
+    /// How many times the form has been submitted.
+    pub fn submit_count(&self) -> u32 {
+        self.submit_count
+    }
+
+    /// Start a submission, returns false if one is already running.
+    pub fn start_submit(&mut self) -> bool {
+        if self.submitting {
+            return false;
+        }
+        self.submitting = true;
+        self.submit_count += 1;
+        true
+    }
+
+    pub fn finish_submit(&mut self) {
+        self.submitting = false;
+    }
+}
+
+/// A model owning a `Form<T>`.
+pub trait FormHost<T>: Sized {
+    fn form(&self) -> &Form<T>;
+
+    fn form_mut(&mut self) -> &mut Form<T>;
+
+    /// Called when the form is submitted with valid values and no other submission is running.
+    ///
+    /// Call `Form::finish_submit` once the values are handled, possibly from a later message. The
+    /// returned value decides whether the container rerenders, as for `Messenger::update`, so
+    /// return true to show the form's `submitting` state.
+    fn submit(
+        &mut self,
+        _sender: &MessageSender<Self>,
+        _render_tx: &Sender<((), oneshot::Sender<()>)>,
+    ) -> bool {
+        self.form_mut().finish_submit();
+        false
+    }
+}
+
+enum Action<T> {
+    Input(Field<T>, String),
+    Check(Field<T, bool>, bool),
+    Touch(&'static str),
+    Submit,
+    Reset,
+    Validated(&'static str, u32, RuleResult),
+}
+
+/// Sent by form controls to the model hosting the form.
+pub struct FormMsg<H, T> {
+    action: Action<T>,
+    host: PhantomData<fn(&mut H)>,
+}
+
+impl<H, T> FormMsg<H, T> {
+    fn new(action: Action<T>) -> Self {
+        FormMsg {
+            action,
+            host: PhantomData,
+        }
+    }
+
+    pub fn input(field: Field<T>, value: String) -> Self {
+        FormMsg::new(Action::Input(field, value))
+    }
+
+    pub fn check(field: Field<T, bool>, value: bool) -> Self {
+        FormMsg::new(Action::Check(field, value))
+    }
+
+    pub fn touch(name: &'static str) -> Self {
+        FormMsg::new(Action::Touch(name))
+    }
+
+    pub fn submit() -> Self {
+        FormMsg::new(Action::Submit)
+    }
+
+    pub fn reset() -> Self {
+        FormMsg::new(Action::Reset)
+    }
+}
+
+impl<H, T> FormMsg<H, T>
+where
+    H: FormHost<T> + 'static,
+    T: Clone + 'static,
+{
+    /// Run the validators of a field, async ones report back with another message.
+    fn validate(form: &mut Form<T>, name: &'static str, sender: &MessageSender<H>) {
+        form.validate_field(name);
+        for (generation, fut) in form.validation.start(name, &form.value) {
+            let sender = sender.clone();
+            spawn_local(async move {
+                let result = fut.await;
+                FormMsg::new(Action::Validated(name, generation, result)).send(&sender);
+            });
+        }
+    }
+
+    /// Hand valid values to the host, `None` if there was nothing to submit.
+    fn submit_valid(
+        target: &mut H,
+        sender: &MessageSender<H>,
+        render_tx: &Sender<((), oneshot::Sender<()>)>,
+    ) -> Option<bool> {
+        let form = target.form_mut();
+        if form.is_valid() && form.start_submit() {
+            Some(target.submit(sender, render_tx))
+        } else {
+            None
+        }
+    }
+}
+
+impl<H, T> Messenger for FormMsg<H, T>
+where
+    H: FormHost<T> + 'static,
+    T: Clone + 'static,
+{
+    type Target = H;
+
+    fn update(
+        self: Box<Self>,
+        target: &mut Self::Target,
+        sender: &MessageSender<Self::Target>,
+        render_tx: &Sender<((), oneshot::Sender<()>)>,
+    ) -> bool {
+        let form = target.form_mut();
+        match self.action {
+            Action::Input(field, value) => {
+                form.set(field, value);
+                if form.validates_input(field.name) {
+                    Self::validate(form, field.name, sender);
+                }
+            }
+            Action::Check(field, value) => {
+                form.set(field, value);
+                if form.validates_input(field.name) {
+                    Self::validate(form, field.name, sender);
+                }
+            }
+            Action::Touch(name) => {
+                let touched = form.touch(name);
+                if !touched || form.validation.mode == ValidateOn::Submit {
+                    return touched;
+                }
+                Self::validate(form, name, sender);
+            }
+            Action::Submit => {
+                form.validation.attempted = true;
+                for name in form.validation.names() {
+                    form.touch(name);
+                    Self::validate(form, name, sender);
+                }
+                if form.validating() {
+                    form.validation.queued_submit = true;
+                } else if let Some(render) = Self::submit_valid(target, sender, render_tx) {
+                    return render;
+                }
+            }
+            Action::Reset => form.reset(),
+            Action::Validated(name, generation, result) => {
+                if !form.validation.finish(name, generation, result) {
+                    return false;
+                }
+                if form.validation.queued_submit && !form.validating() {
+                    form.validation.queued_submit = false;
+                    if let Some(render) = Self::submit_valid(target, sender, render_tx) {
+                        return render;
+                    }
+                }
+            }
+        }
+        true
+    }
+}
+
+impl<T: Clone + 'static> Form<T> {
+    fn control<'a, H>(
+        &self,
+        bump: &'a Bump,
+        tag: &'a str,
+        field: Field<T>,
+        sender: &MessageSender<H>,
+    ) -> ControlBuilder<'a>
+    where
+        H: FormHost<T> + 'static,
+    {
+        let value = bumpalo::collections::String::from_str_in(&field.get(&self.value), bump);
+        ElementBuilder::new(bump, tag)
+            .attr("name", field.name)
+            .attr("value", value.into_bump_str())
+            .attr("aria-invalid", self.aria_invalid(field.name))
+            .on(
+                "input",
+                events::on_input(move |value| FormMsg::input(field, value), sender),
+            )
+            .on("blur", self.on_blur(field.name, sender))
+    }
+
+    fn aria_invalid(&self, name: &str) -> &'static str {
+        if self.has_errors(name) {
+            "true"
+        } else {
+            "false"
+        }
+    }
+
+    fn on_blur<H>(
+        &self,
+        name: &'static str,
+        sender: &MessageSender<H>,
+    ) -> impl Fn(&mut dyn RootRender, VdomWeak, Event) + 'static
+    where
+        H: FormHost<T> + 'static,
+    {
+        events::on_event(move |_: Event| Some(FormMsg::touch(name)), sender)
+    }
+
+    /// An `<input>` bound to `field`, add a `type` attribute for anything but text.
+    pub fn input<'a, H>(
+        &self,
+        bump: &'a Bump,
+        field: Field<T>,
+        sender: &MessageSender<H>,
+    ) -> ControlBuilder<'a>
+    where
+        H: FormHost<T> + 'static,
+    {
+        self.control(bump, "input", field, sender)
+    }
+
+    pub fn textarea<'a, H>(
+        &self,
+        bump: &'a Bump,
+        field: Field<T>,
+        sender: &MessageSender<H>,
+    ) -> ControlBuilder<'a>
+    where
+        H: FormHost<T> + 'static,
+    {
+        self.control(bump, "textarea", field, sender)
+    }
+
+    /// A `<select>` bound to `field`, `options` are `(value, label)` pairs.
+    pub fn select<'a, H>(
+        &self,
+        bump: &'a Bump,
+        field: Field<T>,
+        options: &[(&'a str, &'a str)],
+        sender: &MessageSender<H>,
+    ) -> ControlBuilder<'a>
+    where
+        H: FormHost<T> + 'static,
+    {
+        let current = field.get(&self.value);
+        options.iter().fold(
+            self.control(bump, "select", field, sender),
+            |select, (value, label)| {
+                select.child(
+                    builder::option(bump)
+                        .attr("value", value)
+                        .bool_attr("selected", *value == current)
+                        .child(text(label))
+                        .finish(),
+                )
+            },
+        )
+    }
+
+    pub fn checkbox<'a, H>(
+        &self,
+        bump: &'a Bump,
+        field: Field<T, bool>,
+        sender: &MessageSender<H>,
+    ) -> ControlBuilder<'a>
+    where
+        H: FormHost<T> + 'static,
+    {
+        builder::input(bump)
+            .attr("type", "checkbox")
+            .attr("name", field.name)
+            .bool_attr("checked", field.get(&self.value))
+            .attr("aria-invalid", self.aria_invalid(field.name))
+            .on(
+                "change",
+                events::on_change_checked(move |value| FormMsg::check(field, value), sender),
+            )
+            .on("blur", self.on_blur(field.name, sender))
+    }
+
+    /// Listener for the `submit` event of the `<form>` element.
+    pub fn on_submit<H>(
+        &self,
+        sender: &MessageSender<H>,
+    ) -> impl Fn(&mut dyn RootRender, VdomWeak, Event) + 'static
+    where
+        H: FormHost<T> + 'static,
+    {
+        events::on_submit(FormMsg::submit, sender)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spawner::NativeSpawner;
+
+    #[derive(Clone, Default)]
+    struct Person {
+        name: String,
+        admin: bool,
+    }
+
+    const NAME: Field<Person> = Field::new("name", |p| p.name.clone(), |p, v| p.name = v);
+    const ADMIN: Field<Person, bool> = Field::new("admin", |p| p.admin, |p, v| p.admin = v);
+
+    struct Editor {
+        form: Form<Person>,
+        saved: Vec<String>,
+    }
+
+    impl FormHost<Person> for Editor {
+        fn form(&self) -> &Form<Person> {
+            &self.form
+        }
+
+        fn form_mut(&mut self) -> &mut Form<Person> {
+            &mut self.form
+        }
+
+        fn submit(
+            &mut self,
+            _sender: &MessageSender<Self>,
+            _render_tx: &Sender<((), oneshot::Sender<()>)>,
+        ) -> bool {
+            self.saved.push(self.form.value().name.clone());
+            self.form.commit();
+            false
+        }
+    }
+
+    #[test]
+    fn tracks_field_state() {
+        let mut form = Form::new(Person::default());
+        form.set(NAME, "ferris".to_string());
+        form.set(ADMIN, true);
+        assert!(form.field("name").dirty && form.field("admin").dirty);
+
+        form.set(ADMIN, false);
+        assert!(!form.field("admin").dirty);
+        assert!(form.touch("name"));
+        assert!(!form.touch("name"));
+
+        form.reset();
+        assert_eq!(form.value().name, "");
+        assert!(!form.is_dirty());
+        assert_eq!(form.field("name"), FieldState::default());
+    }
+
+    #[test]
+    fn messages_update_the_host() {
+        let (sender, _) = mpsc::unbounded();
+        let (render_tx, _) = mpsc::unbounded();
+        let mut editor = Editor {
+            form: Form::new(Person::default()),
+            saved: vec![],
+        };
+        let send = |editor: &mut Editor, msg: FormMsg<Editor, Person>| {
+            Box::new(msg).update(editor, &sender, &render_tx)
+        };
+
+        assert!(send(&mut editor, FormMsg::input(NAME, "ferris".into())));
+        assert!(send(&mut editor, FormMsg::check(ADMIN, true)));
+        assert!(send(&mut editor, FormMsg::touch("name")));
+        assert!(!send(&mut editor, FormMsg::touch("name")));
+        assert!(editor.form.is_dirty());
+
+        // the host decides whether to rerender
+        assert!(!send(&mut editor, FormMsg::submit()));
+        // the host didn't finish the submission, so a second one is ignored
+        send(&mut editor, FormMsg::submit());
+        assert_eq!(editor.saved, vec!["ferris".to_string()]);
+        assert_eq!(editor.form.submit_count(), 1);
+        assert!(editor.form.submitting() && !editor.form.is_dirty());
+    }
+
+    fn editor(form: Form<Person>) -> Editor {
+        Editor {
+            form,
+            saved: vec![],
+        }
+    }
+
+    #[test]
+    fn submit_refuses_invalid_values() {
+        let (sender, _) = mpsc::unbounded();
+        let (render_tx, _) = mpsc::unbounded();
+        let form = Form::new(Person::default())
+            .rule(NAME, required("required"))
+            .rule(NAME, length(3, 10, "3 to 10 characters"))
+            .check("admin", |p| match p.admin && p.name != "root" {
+                true => Err("only root can be admin".into()),
+                false => Ok(()),
+            });
+        let mut editor = editor(form);
+        let send = |editor: &mut Editor, msg: FormMsg<Editor, Person>| {
+            Box::new(msg).update(editor, &sender, &render_tx)
+        };
+
+        // validated on blur by default
+        send(&mut editor, FormMsg::input(NAME, "ab".into()));
+        assert!(!editor.form.has_errors("name"));
+        send(&mut editor, FormMsg::touch("name"));
+        assert_eq!(
+            editor.form.errors("name").collect::<Vec<_>>(),
+            vec!["3 to 10 characters"]
+        );
+
+        send(&mut editor, FormMsg::check(ADMIN, true));
+        send(&mut editor, FormMsg::submit());
+        assert!(editor.saved.is_empty() && !editor.form.submitting());
+        assert!(editor.form.has_errors("admin"));
+
+        // cross-field checks follow the fields they depend on
+        send(&mut editor, FormMsg::input(NAME, "root".into()));
+        assert!(editor.form.is_valid());
+        send(&mut editor, FormMsg::submit());
+        assert_eq!(editor.saved, vec!["root".to_string()]);
+    }
+
+    #[test]
+    fn pattern_fails_on_invalid_source() {
+        let email = pattern(r"^[^@\s]+@[^@\s]+$", "not an email address");
+        assert_eq!(email.check(&String::new()), Ok(()));
+        assert_eq!(email.check(&"ferris@rust".to_string()), Ok(()));
+        assert_eq!(
+            email.check(&"ferris".to_string()),
+            Err("not an email address".to_string())
+        );
+
+        let broken = pattern("(unclosed", "not shown");
+        assert_eq!(
+            broken.check(&String::new()),
+            Err("invalid pattern `(unclosed`".to_string())
+        );
+    }
+
+    #[test]
+    fn async_rules_delay_submit() {
+        let spawner = NativeSpawner::current();
+        let (sender, mut receiver) = mpsc::unbounded();
+        let (render_tx, _) = mpsc::unbounded();
+        let form = Form::new(Person::default())
+            .validate_on(ValidateOn::Change)
+            .async_rule(NAME, |name: String| async move {
+                match name.as_str() {
+                    "taken" => Err("name is taken".to_string()),
+                    _ => Ok(()),
+                }
+            });
+        let mut editor = editor(form);
+        let mut run = |editor: &mut Editor, msg: Message<Editor>| {
+            msg.update(editor, &sender, &render_tx);
+            spawner.run_until_stalled();
+            while let Ok((msg, _)) = receiver.try_recv() {
+                msg.update(editor, &sender, &render_tx);
+            }
+        };
+
+        run(&mut editor, Box::new(FormMsg::input(NAME, "taken".into())));
+        assert_eq!(
+            editor.form.errors("name").collect::<Vec<_>>(),
+            vec!["name is taken"]
+        );
+
+        run(&mut editor, Box::new(FormMsg::input(NAME, "free".into())));
+        assert!(editor.form.is_valid());
+        run(&mut editor, Box::new(FormMsg::submit()));
+        assert_eq!(editor.saved, vec!["free".to_string()]);
+    }
+}
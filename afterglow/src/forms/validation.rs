@@ -0,0 +1,371 @@
+//! Field and cross-field validation for `Form`.
+//!
+//! ```ignore
+//! Form::new(Person::default())
+//!     .validate_on(ValidateOn::Blur)
+//!     .rule(NAME, required("a name is required"))
+//!     .rule(NAME, length(2, 40, "between 2 and 40 characters"))
+//!     .rule(EMAIL, pattern(r"^[^@\s]+@[^@\s]+$", "not an email address"))
+//!     .async_rule(EMAIL, |email| api::email_available(email))
+//!     .check("confirm", |p| if p.password == p.confirm { Ok(()) } else { Err("passwords differ".into()) })
+//! ```
+
+use super::{Field, Form};
+use crate::prelude::*;
+use dodrio::builder;
+use dodrio::bumpalo::{self, Bump};
+use futures::future::LocalBoxFuture;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+pub type RuleResult = Result<(), String>;
+
+/// Checks a single value.
+pub trait Rule<V> {
+    fn check(&self, value: &V) -> RuleResult;
+}
+
+impl<V, F> Rule<V> for F
+where
+    F: Fn(&V) -> RuleResult,
+{
+    fn check(&self, value: &V) -> RuleResult {
+        self(value)
+    }
+}
+
+/// When fields are validated, submitting always validates every field.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ValidateOn {
+    /// On every input.
+    Change,
+    /// Once a field loses focus, then on every input.
+    #[default]
+    Blur,
+    /// Only on submit, then on every input.
+    Submit,
+}
+
+pub fn required(message: impl Into<String>) -> impl Rule<String> {
+    let message = message.into();
+    move |value: &String| {
+        if value.trim().is_empty() {
+            Err(message.clone())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// The number of characters must be in `min..=max`.
+pub fn length(min: usize, max: usize, message: impl Into<String>) -> impl Rule<String> {
+    let message = message.into();
+    move |value: &String| {
+        let len = value.chars().count();
+        if len < min || len > max {
+            Err(message.clone())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Test the value against a regular expression, empty values pass so `required` decides whether
+/// they are allowed.
+///
+/// Browsers use javascript's syntax, native targets the `regex` crate's, so stick to what both
+/// understand. An invalid pattern is logged and fails every value.
+pub fn pattern(source: &str, message: impl Into<String>) -> impl Rule<String> {
+    let message = message.into();
+    let regex = compile(source).map_err(|err| {
+        log::error!("invalid pattern `{}`: {}", source, err);
+        format!("invalid pattern `{}`", source)
+    });
+    move |value: &String| match regex.as_ref() {
+        Ok(regex) if value.is_empty() || regex.test(value) => Ok(()),
+        Ok(_) => Err(message.clone()),
+        Err(err) => Err(err.clone()),
+    }
+}
+
+/// Build a `RegExp` without letting a syntax error escape as an exception.
+#[cfg(target_arch = "wasm32")]
+fn compile(source: &str) -> Result<js_sys::RegExp, String> {
+    let constructor = js_sys::Reflect::get(&js_sys::global(), &"RegExp".into())
+        .map_err(|_| "RegExp is missing".to_string())?;
+    let args = js_sys::Array::of2(&source.into(), &"u".into());
+    js_sys::Reflect::construct(constructor.unchecked_ref::<js_sys::Function>(), &args)
+        .map(JsCast::unchecked_into)
+        .map_err(|err| match err.dyn_into::<js_sys::Error>() {
+            Ok(err) => String::from(err.message()),
+            Err(_) => "not a regular expression".to_string(),
+        })
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn compile(source: &str) -> Result<Regex, String> {
+    regex::Regex::new(source)
+        .map(Regex)
+        .map_err(|err| err.to_string())
+}
+
+/// `regex::Regex` with `RegExp`'s method name.
+#[cfg(not(target_arch = "wasm32"))]
+struct Regex(regex::Regex);
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Regex {
+    fn test(&self, value: &str) -> bool {
+        self.0.is_match(value)
+    }
+}
+
+type SyncRule<T> = Rc<dyn Fn(&T) -> RuleResult>;
+type AsyncRule<T> = Rc<dyn Fn(&T) -> LocalBoxFuture<'static, RuleResult>>;
+
+struct Pending {
+    generation: u32,
+    remaining: usize,
+}
+
+/// Validation state kept by a `Form`.
+pub(crate) struct Validation<T> {
+    pub(crate) mode: ValidateOn,
+    rules: Vec<(&'static str, SyncRule<T>)>,
+    async_rules: Vec<(&'static str, AsyncRule<T>)>,
+    /// Fields with cross-field checks, revalidated whenever another field changes.
+    cross: Vec<&'static str>,
+    errors: HashMap<&'static str, Vec<String>>,
+    async_errors: HashMap<&'static str, Vec<String>>,
+    pending: HashMap<&'static str, Pending>,
+    generation: u32,
+    /// A submit waits for async validators to finish.
+    pub(crate) queued_submit: bool,
+    /// Submitting was attempted, fields are validated on every input from then on.
+    pub(crate) attempted: bool,
+}
+
+impl<T> Default for Validation<T> {
+    fn default() -> Self {
+        Validation {
+            mode: ValidateOn::default(),
+            rules: vec![],
+            async_rules: vec![],
+            cross: vec![],
+            errors: HashMap::new(),
+            async_errors: HashMap::new(),
+            pending: HashMap::new(),
+            generation: 0,
+            queued_submit: false,
+            attempted: false,
+        }
+    }
+}
+
+impl<T> Validation<T> {
+    pub(crate) fn clear(&mut self) {
+        self.errors.clear();
+        self.async_errors.clear();
+        self.pending.clear();
+        self.queued_submit = false;
+        self.attempted = false;
+    }
+
+    pub(crate) fn names(&self) -> Vec<&'static str> {
+        let mut names: Vec<_> = self
+            .rules
+            .iter()
+            .map(|(name, _)| *name)
+            .chain(self.async_rules.iter().map(|(name, _)| *name))
+            .collect();
+        names.sort_unstable();
+        names.dedup();
+        names
+    }
+
+    fn run(&mut self, name: &'static str, value: &T) -> bool {
+        let errors: Vec<_> = self
+            .rules
+            .iter()
+            .filter(|(field, _)| *field == name)
+            .filter_map(|(_, rule)| rule(value).err())
+            .collect();
+        if errors.is_empty() {
+            self.errors.remove(name).is_some()
+        } else {
+            self.errors.insert(name, errors);
+            true
+        }
+    }
+
+    /// Start the async validators of a field, results are reported with `finish`.
+    pub(crate) fn start(
+        &mut self,
+        name: &'static str,
+        value: &T,
+    ) -> Vec<(u32, LocalBoxFuture<'static, RuleResult>)> {
+        let futures: Vec<_> = self
+            .async_rules
+            .iter()
+            .filter(|(field, _)| *field == name)
+            .map(|(_, rule)| rule(value))
+            .collect();
+        self.async_errors.remove(name);
+        if futures.is_empty() {
+            return vec![];
+        }
+        self.generation += 1;
+        let generation = self.generation;
+        self.pending.insert(
+            name,
+            Pending {
+                generation,
+                remaining: futures.len(),
+            },
+        );
+        futures.into_iter().map(|fut| (generation, fut)).collect()
+    }
+
+    /// Record an async result, returns false if it is outdated.
+    pub(crate) fn finish(
+        &mut self,
+        name: &'static str,
+        generation: u32,
+        result: RuleResult,
+    ) -> bool {
+        match self.pending.get_mut(name) {
+            Some(pending) if pending.generation == generation => {
+                pending.remaining -= 1;
+                if pending.remaining == 0 {
+                    self.pending.remove(name);
+                }
+            }
+            _ => return false,
+        }
+        if let Err(error) = result {
+            self.async_errors.entry(name).or_default().push(error);
+        }
+        true
+    }
+
+    pub(crate) fn is_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+}
+
+impl<T: 'static> Form<T> {
+    pub fn validate_on(mut self, mode: ValidateOn) -> Self {
+        self.validation.mode = mode;
+        self
+    }
+
+    /// Validate a field with `rule`, a field can have any number of rules.
+    pub fn rule<V: 'static>(mut self, field: Field<T, V>, rule: impl Rule<V> + 'static) -> Self {
+        self.validation.rules.push((
+            field.name,
+            Rc::new(move |value| rule.check(&field.get(value))),
+        ));
+        self
+    }
+
+    /// A rule that sees every field, errors are shown under `name`.
+    pub fn check(mut self, name: &'static str, check: impl Fn(&T) -> RuleResult + 'static) -> Self {
+        self.validation.rules.push((name, Rc::new(check)));
+        self.validation.cross.push(name);
+        self
+    }
+
+    /// Validate a field in the background, e.g. by asking a server.
+    ///
+    /// Results for a value that changed in the meantime are dropped.
+    pub fn async_rule<V, F>(mut self, field: Field<T, V>, rule: impl Fn(V) -> F + 'static) -> Self
+    where
+        V: 'static,
+        F: Future<Output = RuleResult> + 'static,
+    {
+        self.validation.async_rules.push((
+            field.name,
+            Rc::new(move |value| rule(field.get(value)).boxed_local()),
+        ));
+        self
+    }
+}
+
+impl<T> Form<T> {
+    pub fn validate_field(&mut self, name: &'static str) -> bool {
+        self.validation.run(name, &self.value);
+        for cross in self.validation.cross.clone() {
+            if cross != name && self.fields.get(cross).is_some_and(|state| state.touched) {
+                self.validation.run(cross, &self.value);
+            }
+        }
+        !self.validation.errors.contains_key(name)
+    }
+
+    /// Run the synchronous rules of every field and mark them as touched.
+    pub fn validate(&mut self) -> bool {
+        for name in self.validation.names() {
+            self.touch(name);
+            self.validation.run(name, &self.value);
+        }
+        self.is_valid()
+    }
+
+    /// No field has errors and no async validator is still running.
+    pub fn is_valid(&self) -> bool {
+        self.validation.errors.is_empty()
+            && self.validation.async_errors.is_empty()
+            && !self.validation.is_pending()
+    }
+
+    /// Some async validator is still running.
+    pub fn validating(&self) -> bool {
+        self.validation.is_pending()
+    }
+
+    pub fn errors<'s>(&'s self, name: &str) -> impl Iterator<Item = &'s str> + 's {
+        self.validation
+            .errors
+            .get(name)
+            .into_iter()
+            .chain(self.validation.async_errors.get(name))
+            .flatten()
+            .map(String::as_str)
+    }
+
+    pub fn has_errors(&self, name: &str) -> bool {
+        self.errors(name).next().is_some()
+    }
+
+    /// Whether a change of `name` should be validated right away.
+    pub(crate) fn validates_input(&self, name: &str) -> bool {
+        match self.validation.mode {
+            ValidateOn::Change => true,
+            _ if self.validation.attempted => true,
+            ValidateOn::Blur => self.field(name).touched,
+            ValidateOn::Submit => false,
+        }
+    }
+
+    /// A `<ul class="errors">` listing the errors of `name`, `None` when there are none.
+    pub fn errors_view<'a>(&self, bump: &'a Bump, name: &'static str) -> Option<Node<'a>> {
+        if !self.has_errors(name) {
+            return None;
+        }
+        let items = self.errors(name).map(|error| {
+            builder::li(bump)
+                .child(text(
+                    bumpalo::collections::String::from_str_in(error, bump).into_bump_str(),
+                ))
+                .finish()
+        });
+        Some(
+            builder::ul(bump)
+                .attr("class", "errors")
+                .attr("data-field", name)
+                .attr("role", "alert")
+                .children(bumpalo::collections::Vec::from_iter_in(items, bump))
+                .finish(),
+        )
+    }
+}
@@ -0,0 +1,434 @@
+//! Document title, meta tags and links declared by containers.
+//!
+//! Models describe their part of `<head>` in `LifeCycle::head`, which is read again whenever an
+//! update asks for a render:
+//!
+//! ```ignore
+//! fn head(&self) -> Option<Head> {
+//!     Some(
+//!         Head::new()
+//!             .title(format!("{} - Inbox", self.unread))
+//!             .meta("description", "Your messages")
+//!             .canonical("https://example.com/inbox"),
+//!     )
+//! }
+//! ```
+//!
+//! Heads of every mounted container are merged from the outermost to the innermost one, so the
+//! innermost container wins for each title, meta tag and canonical link. Stylesheets add up.
+//! Whatever was changed is restored once the containers are destroyed.
+
+use crate::renderer::Invalidator;
+use std::cell::RefCell;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum MetaKey {
+    Name(String),
+    Property(String),
+}
+
+/// Part of the document's `<head>`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Head {
+    title: Option<String>,
+    metas: Vec<(MetaKey, String)>,
+    /// `rel` and `href`, canonical and other links replace earlier ones, stylesheets add up.
+    links: Vec<(String, String)>,
+}
+
+impl Head {
+    pub fn new() -> Self {
+        Head::default()
+    }
+
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    /// `<meta name=.. content=..>`, e.g. `description`.
+    pub fn meta(self, name: impl Into<String>, content: impl Into<String>) -> Self {
+        self.with_meta(MetaKey::Name(name.into()), content.into())
+    }
+
+    /// `<meta property=.. content=..>`, e.g. `og:title`.
+    pub fn property(self, property: impl Into<String>, content: impl Into<String>) -> Self {
+        self.with_meta(MetaKey::Property(property.into()), content.into())
+    }
+
+    fn with_meta(mut self, key: MetaKey, content: String) -> Self {
+        match self.metas.iter_mut().find(|(known, _)| *known == key) {
+            Some((_, known)) => *known = content,
+            None => self.metas.push((key, content)),
+        }
+        self
+    }
+
+    pub fn canonical(self, href: impl Into<String>) -> Self {
+        self.link("canonical", href)
+    }
+
+    pub fn stylesheet(self, href: impl Into<String>) -> Self {
+        self.link("stylesheet", href)
+    }
+
+    pub fn link(mut self, rel: impl Into<String>, href: impl Into<String>) -> Self {
+        let (rel, href) = (rel.into(), href.into());
+        if rel == "stylesheet" {
+            if !self
+                .links
+                .iter()
+                .any(|link| link.0 == rel && link.1 == href)
+            {
+                self.links.push((rel, href));
+            }
+        } else {
+            match self.links.iter_mut().find(|link| link.0 == rel) {
+                Some(link) => link.1 = href,
+                None => self.links.push((rel, href)),
+            }
+        }
+        self
+    }
+
+    /// Lay a nested container's head over this one.
+    fn merge(mut self, inner: &Head) -> Self {
+        if inner.title.is_some() {
+            self.title = inner.title.clone();
+        }
+        for (key, content) in inner.metas.iter() {
+            self = self.with_meta(key.clone(), content.clone());
+        }
+        for (rel, href) in inner.links.iter() {
+            self = self.link(rel.clone(), href.clone());
+        }
+        self
+    }
+}
+
+/// Changes made to the document, so they can be undone.
+#[derive(Default)]
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+struct Applied {
+    head: Head,
+    title: Option<String>,
+    created: Vec<web_sys::Element>,
+    changed: Vec<(web_sys::Element, &'static str, Option<String>)>,
+}
+
+#[derive(Default)]
+struct Registry {
+    next_id: u32,
+    /// Id, cache and head of each container that declared one. The cache knows the container's
+    /// parents once it rendered, so nesting is looked up whenever the heads are merged.
+    entries: Vec<(u32, Invalidator, Head)>,
+    applied: Applied,
+}
+
+impl Registry {
+    fn resolved(&self) -> Head {
+        let mut entries: Vec<_> = self
+            .entries
+            .iter()
+            .map(|(_, cache, head)| (cache.depth(), head))
+            .collect();
+        // stable, so later containers win among siblings
+        entries.sort_by_key(|(depth, _)| *depth);
+        entries
+            .into_iter()
+            .fold(Head::default(), |head, (_, inner)| head.merge(inner))
+    }
+
+    fn apply(&mut self) {
+        let head = self.resolved();
+        if head == self.applied.head {
+            return;
+        }
+        let mut applied = std::mem::take(&mut self.applied);
+        revert(&mut applied);
+        write(&head, &mut applied);
+        applied.head = head;
+        self.applied = applied;
+    }
+}
+
+thread_local! {
+    static REGISTRY: RefCell<Registry> = RefCell::new(Registry::default());
+}
+
+#[cfg(target_arch = "wasm32")]
+fn revert(applied: &mut Applied) {
+    let document = match web_sys::window().and_then(|window| window.document()) {
+        Some(document) => document,
+        None => return,
+    };
+    if let Some(title) = applied.title.take() {
+        document.set_title(&title);
+    }
+    for element in applied.created.drain(..) {
+        element.remove();
+    }
+    for (element, attr, value) in applied.changed.drain(..).rev() {
+        let _ = match value {
+            Some(value) => element.set_attribute(attr, &value),
+            None => element.remove_attribute(attr),
+        };
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn revert(_applied: &mut Applied) {}
+
+/// Quote `value` for an attribute selector, escaping like `CSS.escape` does.
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+fn css_string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '\0' => quoted.push('\u{fffd}'),
+            '"' | '\\' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            c if c.is_control() => quoted.push_str(&format!("\\{:x} ", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Point an existing element's attribute at `value`, or create the element.
+#[cfg(target_arch = "wasm32")]
+fn upsert(
+    applied: &mut Applied,
+    selector: &str,
+    create: impl FnOnce() -> Option<web_sys::Element>,
+    attr: &'static str,
+    value: &str,
+) {
+    let document = match web_sys::window().and_then(|window| window.document()) {
+        Some(document) => document,
+        None => return,
+    };
+    if let Some(element) = document
+        .head()
+        .and_then(|head| head.query_selector(selector).ok()?)
+    {
+        if element.get_attribute(attr).as_deref() != Some(value) {
+            applied
+                .changed
+                .push((element.clone(), attr, element.get_attribute(attr)));
+            let _ = element.set_attribute(attr, value);
+        }
+        return;
+    }
+    let element = match create() {
+        Some(element) => element,
+        None => return,
+    };
+    let _ = element.set_attribute(attr, value);
+    let _ = element.set_attribute("data-afterglow-head", "");
+    if let Some(head) = document.head() {
+        let _ = head.append_child(&element);
+        applied.created.push(element);
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn write(head: &Head, applied: &mut Applied) {
+    let document = match web_sys::window().and_then(|window| window.document()) {
+        Some(document) => document,
+        None => return,
+    };
+    if let Some(title) = head.title.as_ref() {
+        applied.title = Some(document.title());
+        document.set_title(title);
+    }
+    let element = |tag: &str, attrs: &[(&str, &str)]| {
+        let element = document.create_element(tag).ok()?;
+        for (name, value) in attrs {
+            element.set_attribute(name, value).ok()?;
+        }
+        Some(element)
+    };
+    for (key, content) in head.metas.iter() {
+        let (attr, name) = match key {
+            MetaKey::Name(name) => ("name", name),
+            MetaKey::Property(property) => ("property", property),
+        };
+        let selector = format!("meta[{}={}]", attr, css_string(name));
+        let create = || element("meta", &[(attr, name.as_str())]);
+        upsert(applied, &selector, create, "content", content);
+    }
+    for (rel, href) in head.links.iter() {
+        let selector = if rel == "stylesheet" {
+            format!("link[rel=\"stylesheet\"][href={}]", css_string(href))
+        } else {
+            format!("link[rel={}]", css_string(rel))
+        };
+        let create = || element("link", &[("rel", rel.as_str())]);
+        upsert(applied, &selector, create, "href", href);
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn write(_head: &Head, _applied: &mut Applied) {}
+
+/// A container's place in the registry, removed on drop.
+pub(crate) struct HeadSlot(u32, Invalidator);
+
+impl HeadSlot {
+    pub(crate) fn new(cache: Invalidator) -> Self {
+        REGISTRY.with(|registry| {
+            let mut registry = registry.borrow_mut();
+            registry.next_id += 1;
+            HeadSlot(registry.next_id, cache)
+        })
+    }
+
+    /// Declare the container's head.
+    pub(crate) fn set(&self, head: Option<Head>) {
+        REGISTRY.with(|registry| {
+            let mut registry = registry.borrow_mut();
+            let index = registry.entries.iter().position(|entry| entry.0 == self.0);
+            match (index, head) {
+                (Some(index), Some(head)) => registry.entries[index].2 = head,
+                (None, Some(head)) => registry.entries.push((self.0, self.1.clone(), head)),
+                (Some(index), None) => {
+                    registry.entries.remove(index);
+                }
+                (None, None) => return,
+            }
+            registry.apply();
+        });
+    }
+}
+
+impl Drop for HeadSlot {
+    fn drop(&mut self) {
+        self.set(None);
+    }
+}
+
+/// Merge the heads again after a container moved under another one.
+pub(crate) fn refresh() {
+    REGISTRY.with(|registry| {
+        let mut registry = registry.borrow_mut();
+        if !registry.entries.is_empty() {
+            registry.apply();
+        }
+    });
+}
+
+/// The head currently applied to the document.
+pub fn current() -> Head {
+    REGISTRY.with(|registry| registry.borrow().applied.head.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{Backend, Html};
+    use crate::prelude::*;
+    use crate::spawner::NativeSpawner;
+    use crate::vnode::{el, View};
+
+    struct Inbox;
+
+    impl LifeCycle for Inbox {
+        fn new(_render_tx: Sender<((), oneshot::Sender<()>)>) -> Self {
+            Inbox
+        }
+
+        fn head(&self) -> Option<Head> {
+            Some(
+                Head::new()
+                    .title("Inbox")
+                    .canonical("/inbox")
+                    .stylesheet("/inbox.css"),
+            )
+        }
+    }
+
+    struct InboxView;
+
+    impl View for InboxView {
+        type Target = Inbox;
+        type Data = Inbox;
+
+        fn render(&self, _target: &Self::Target, _sender: &MessageSender<Self::Data>) -> VNode {
+            el("section").finish()
+        }
+    }
+
+    struct App {
+        page: Option<Container<Inbox>>,
+    }
+
+    impl LifeCycle for App {
+        fn new(render_tx: Sender<((), oneshot::Sender<()>)>) -> Self {
+            // created before the app's own container, like children usually are
+            let page = Container::new(Inbox, Box::new(InboxView), render_tx);
+            App { page: Some(page) }
+        }
+
+        fn head(&self) -> Option<Head> {
+            Some(
+                Head::new()
+                    .title("App")
+                    .meta("description", "An app")
+                    .stylesheet("/app.css"),
+            )
+        }
+    }
+
+    struct AppView;
+
+    impl View for AppView {
+        type Target = App;
+        type Data = App;
+
+        fn render(&self, target: &Self::Target, _sender: &MessageSender<Self::Data>) -> VNode {
+            el("main")
+                .children(target.page.iter().map(Container::node))
+                .finish()
+        }
+    }
+
+    #[test]
+    fn selector_values_are_escaped() {
+        assert_eq!(css_string("og:title"), "\"og:title\"");
+        assert_eq!(css_string("/a\"b]\\c\nd"), "\"/a\\\"b]\\\\c\\a d\"");
+    }
+
+    #[test]
+    fn innermost_container_wins() {
+        let spawner = NativeSpawner::current();
+        let (render_tx, _) = mpsc::unbounded();
+        let app = Container::new(App::new(render_tx.clone()), Box::new(AppView), render_tx);
+        spawner.run_until_stalled();
+        Html.build(app.node());
+
+        let expected = Head::new()
+            .title("Inbox")
+            .meta("description", "An app")
+            .stylesheet("/app.css")
+            .canonical("/inbox")
+            .stylesheet("/inbox.css");
+        assert_eq!(current(), expected);
+
+        app.data.update(|app| app.page = None).unwrap();
+        assert_eq!(
+            current(),
+            Head::new()
+                .title("App")
+                .meta("description", "An app")
+                .stylesheet("/app.css")
+        );
+        drop(app);
+        spawner.run_until_stalled();
+        assert_eq!(current(), Head::default());
+    }
+}
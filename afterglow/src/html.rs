@@ -0,0 +1,260 @@
+//! Typed view macro for renderers.
+//!
+//! `html!(bump, sender, <root>...</root>)` expands into `dodrio::builder` calls. The sender can be
+//! left out when no `on:` bindings are used.
+//!
+//! ```ignore
+//! html!(ctx.bump, sender,
+//!     <div class="card">
+//!         <p class="title">{ bf!(in bump, "clicked {} times", target.clicked).into_bump_str() }</p>
+//!         if target.status { <span class="tag">"on"</span> } else { <span>"off"</span> }
+//!         <ul>
+//!             for item in target.items.iter() { <li key={item.id}>{ &item.name }</li> }
+//!         </ul>
+//!         <button disabled={target.busy} on:click={|_| ClickMsg::Clicked}>"click"</button>
+//!         <a listen:click={route_to("home")}>"home"</a>
+//!     </div>
+//! )
+//! ```
+//!
+//! - Elements map to the functions of `dodrio::builder`, so unknown tags fail to compile. Dashed
+//!   tags are treated as custom elements.
+//! - Attributes are checked against the known html and svg attributes, `data-*` and `aria-*` are
+//!   always accepted.
+//! - `{expr}` in attribute position accepts anything implementing `IntoAttr`, `false` and `None`
+//!   leave the attribute out.
+//! - `{expr}` in child position accepts anything implementing `IntoNodes`.
+//! - `<>...</>` at the root builds a `bumpalo::collections::Vec` of sibling nodes for
+//!   `Renderer::view_children`, nested ones splice their children into the parent.
+//! - `on:event={|e| Msg}` converts the event into a `Messenger` like `consume` does,
+//!   `listen:event={f}` attaches a raw dodrio listener.
+//!
+//! ```compile_fail
+//! use afterglow::prelude::*;
+//!
+//! fn view<'a>(bump: &'a dodrio::bumpalo::Bump) -> Node<'a> {
+//!     html!(bump, <div colour="red"></div>)
+//! }
+//! ```
+
+use crate::prelude::*;
+use dodrio::bumpalo::{self, Bump};
+
+pub use afterglow_macro::html;
+
+/// Values that can be spliced into an element's children.
+pub trait IntoNodes<'a> {
+    fn into_nodes(self, bump: &'a Bump, nodes: &mut bumpalo::collections::Vec<'a, Node<'a>>);
+}
+
+impl<'a> IntoNodes<'a> for Node<'a> {
+    fn into_nodes(self, _: &'a Bump, nodes: &mut bumpalo::collections::Vec<'a, Node<'a>>) {
+        nodes.push(self);
+    }
+}
+
+impl<'a, T: IntoNodes<'a>> IntoNodes<'a> for Option<T> {
+    fn into_nodes(self, bump: &'a Bump, nodes: &mut bumpalo::collections::Vec<'a, Node<'a>>) {
+        if let Some(inner) = self {
+            inner.into_nodes(bump, nodes);
+        }
+    }
+}
+
+impl<'a, T: IntoNodes<'a>> IntoNodes<'a> for Vec<T> {
+    fn into_nodes(self, bump: &'a Bump, nodes: &mut bumpalo::collections::Vec<'a, Node<'a>>) {
+        for inner in self {
+            inner.into_nodes(bump, nodes);
+        }
+    }
+}
+
+impl<'a> IntoNodes<'a> for bumpalo::collections::Vec<'a, Node<'a>> {
+    fn into_nodes(self, _: &'a Bump, nodes: &mut bumpalo::collections::Vec<'a, Node<'a>>) {
+        nodes.extend(self);
+    }
+}
+
+impl<'a> IntoNodes<'a> for &str {
+    fn into_nodes(self, bump: &'a Bump, nodes: &mut bumpalo::collections::Vec<'a, Node<'a>>) {
+        nodes.push(text(
+            bumpalo::collections::String::from_str_in(self, bump).into_bump_str(),
+        ));
+    }
+}
+
+impl<'a> IntoNodes<'a> for &String {
+    fn into_nodes(self, bump: &'a Bump, nodes: &mut bumpalo::collections::Vec<'a, Node<'a>>) {
+        self.as_str().into_nodes(bump, nodes);
+    }
+}
+
+impl<'a> IntoNodes<'a> for String {
+    fn into_nodes(self, bump: &'a Bump, nodes: &mut bumpalo::collections::Vec<'a, Node<'a>>) {
+        self.as_str().into_nodes(bump, nodes);
+    }
+}
+
+/// Values usable as an attribute, `None` leaves the attribute out.
+pub trait IntoAttr<'a> {
+    fn into_attr(self, bump: &'a Bump) -> Option<&'a str>;
+}
+
+impl<'a> IntoAttr<'a> for &str {
+    fn into_attr(self, bump: &'a Bump) -> Option<&'a str> {
+        Some(bumpalo::collections::String::from_str_in(self, bump).into_bump_str())
+    }
+}
+
+impl<'a> IntoAttr<'a> for &String {
+    fn into_attr(self, bump: &'a Bump) -> Option<&'a str> {
+        self.as_str().into_attr(bump)
+    }
+}
+
+impl<'a> IntoAttr<'a> for String {
+    fn into_attr(self, bump: &'a Bump) -> Option<&'a str> {
+        self.as_str().into_attr(bump)
+    }
+}
+
+/// Boolean attributes such as `disabled` or `checked` are present only when true.
+impl<'a> IntoAttr<'a> for bool {
+    fn into_attr(self, _: &'a Bump) -> Option<&'a str> {
+        if self {
+            Some("")
+        } else {
+            None
+        }
+    }
+}
+
+impl<'a, T: IntoAttr<'a>> IntoAttr<'a> for Option<T> {
+    fn into_attr(self, bump: &'a Bump) -> Option<&'a str> {
+        self.and_then(|inner| inner.into_attr(bump))
+    }
+}
+
+macro_rules! display_impls {
+    ($($ty:ty),*) => {
+        $(
+            impl<'a> IntoNodes<'a> for $ty {
+                fn into_nodes(self, bump: &'a Bump, nodes: &mut bumpalo::collections::Vec<'a, Node<'a>>) {
+                    nodes.push(text(bf!(in bump, "{}", self).into_bump_str()));
+                }
+            }
+
+            impl<'a> IntoAttr<'a> for $ty {
+                fn into_attr(self, bump: &'a Bump) -> Option<&'a str> {
+                    Some(bf!(in bump, "{}", self).into_bump_str())
+                }
+            }
+        )*
+    };
+}
+
+display_impls!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize, f32, f64, char);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    pub struct Item {
+        id: u32,
+        name: String,
+    }
+
+    pub enum ClickMsg {
+        Clicked,
+    }
+
+    impl Messenger for ClickMsg {
+        type Target = Vec<Item>;
+    }
+
+    fn view<'a>(
+        bump: &'a Bump,
+        items: &[Item],
+        show: bool,
+        sender: &MessageSender<Vec<Item>>,
+    ) -> Node<'a> {
+        html!(bump, sender,
+            <div class="list" aria-label="items" hidden={!show}>
+                "items:"
+                { items.len() }
+                if items.is_empty() { <p>"empty"</p> } else if show { <ul>
+                    for item in items.iter() {
+                        <li key={item.id} data-id={item.id}>{ &item.name }</li>
+                    }
+                </ul> }
+                <button on:click={|_| ClickMsg::Clicked} disabled>"add"</button>
+                <my-widget/>
+            </div>
+        )
+    }
+
+    fn rows<'a>(bump: &'a Bump, items: &[Item]) -> bumpalo::collections::Vec<'a, Node<'a>> {
+        html!(bump,
+            <>
+                for item in items.iter() {
+                    <>
+                        <dt>{ item.id }</dt>
+                        <dd>{ &item.name }</dd>
+                    </>
+                }
+            </>
+        )
+    }
+
+    #[test]
+    fn html_macro_builds_fragments() {
+        let bump = Bump::new();
+        let items = vec![Item {
+            id: 7,
+            name: "seventh".into(),
+        }];
+        let nodes = rows(&bump, &items);
+        assert_eq!(nodes.len(), 2);
+        let nodes = format!("{:?}", nodes);
+        assert!(nodes.contains("tag_name: \"dt\""));
+        assert!(nodes.contains("text: \"seventh\""));
+    }
+
+    #[test]
+    fn html_macro_builds_nodes() {
+        let bump = Bump::new();
+        let (sender, _) = mpsc::unbounded();
+        let items = vec![
+            Item {
+                id: 1,
+                name: "first".into(),
+            },
+            Item {
+                id: 2,
+                name: "second".into(),
+            },
+        ];
+        let node = format!("{:?}", view(&bump, &items, true, &sender));
+
+        for expected in &[
+            "tag_name: \"div\"",
+            "name: \"aria-label\"",
+            "text: \"items:\"",
+            "text: \"2\"",
+            "tag_name: \"li\"",
+            "name: \"data-id\", value: \"2\"",
+            "text: \"second\"",
+            "tag_name: \"button\"",
+            "event: \"click\"",
+            "tag_name: \"my-widget\"",
+        ] {
+            assert!(node.contains(expected), "missing {} in {}", expected, node);
+        }
+        assert!(!node.contains("hidden"));
+        assert!(!node.contains("empty"));
+
+        let node = format!("{:?}", view(&bump, &[], false, &sender));
+        assert!(node.contains("name: \"hidden\""));
+        assert!(node.contains("text: \"empty\""));
+    }
+}
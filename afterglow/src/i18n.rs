@@ -0,0 +1,480 @@
+//! Translated strings for renderers.
+//!
+//! Catalogs use a small key/value format, one message per line, `#` starts a comment. Plural
+//! forms add the CLDR category to the key, and `{name}` is replaced by an argument:
+//!
+//! ```text
+//! greeting = Hello, {name}!
+//! inbox.one = {count} new message
+//! inbox.other = {count} new messages
+//! ```
+//!
+//! Renderers translate into the bump allocator with `t!`, the container being rendered is redrawn
+//! once the locale changes:
+//!
+//! ```ignore
+//! html!(bump, <p>{ t!(bump, "greeting", name = target.name) }</p>
+//!     <p>{ t!(bump, "inbox", count = target.unread) }</p>)
+//! ```
+//!
+//! Switch locales with `EntryMessage::SetLocale`, or from any container with the `SetLocale`
+//! messenger. Catalogs loaded later, e.g. fetched on demand, are added the same way with
+//! `EntryMessage::AddCatalog` or `AddCatalog`.
+
+use crate::prelude::*;
+use crate::renderer::Dependents;
+use crate::vnode::Str;
+use dodrio::bumpalo::{self, Bump};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::fmt::{self, Display, Write};
+use std::marker::PhantomData;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Plural {
+    Zero,
+    One,
+    Two,
+    Few,
+    Many,
+    Other,
+}
+
+impl Plural {
+    /// The CLDR plural category of `n` for a locale, only the common rules are known and every
+    /// other language uses the english one.
+    pub fn of(locale: &str, n: u64) -> Plural {
+        let language = locale.split(['-', '_']).next().unwrap_or_default();
+        match language {
+            "ja" | "ko" | "zh" | "th" | "vi" | "id" => Plural::Other,
+            "fr" | "pt" if n <= 1 => Plural::One,
+            "fr" | "pt" => Plural::Other,
+            "ru" | "uk" | "be" | "sr" | "hr" | "bs" => match (n % 10, n % 100) {
+                (1, rem) if rem != 11 => Plural::One,
+                (2..=4, rem) if !(12..=14).contains(&rem) => Plural::Few,
+                _ => Plural::Many,
+            },
+            "pl" => match (n, n % 10, n % 100) {
+                (1, _, _) => Plural::One,
+                (_, 2..=4, rem) if !(12..=14).contains(&rem) => Plural::Few,
+                _ => Plural::Many,
+            },
+            "cs" | "sk" => match n {
+                1 => Plural::One,
+                2..=4 => Plural::Few,
+                _ => Plural::Other,
+            },
+            "ar" => match (n, n % 100) {
+                (0, _) => Plural::Zero,
+                (1, _) => Plural::One,
+                (2, _) => Plural::Two,
+                (_, 3..=10) => Plural::Few,
+                (_, 11..=99) => Plural::Many,
+                _ => Plural::Other,
+            },
+            _ if n == 1 => Plural::One,
+            _ => Plural::Other,
+        }
+    }
+
+    fn suffix(self) -> &'static str {
+        match self {
+            Plural::Zero => "zero",
+            Plural::One => "one",
+            Plural::Two => "two",
+            Plural::Few => "few",
+            Plural::Many => "many",
+            Plural::Other => "other",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Segment {
+    Text(String),
+    Arg(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Template(Vec<Segment>);
+
+impl Template {
+    fn parse(source: &str) -> Result<Self, &'static str> {
+        let mut segments = vec![];
+        let mut text = String::new();
+        let mut chars = source.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => return Err("unclosed `{`"),
+                        }
+                    }
+                    if !text.is_empty() {
+                        segments.push(Segment::Text(std::mem::take(&mut text)));
+                    }
+                    segments.push(Segment::Arg(name.trim().to_string()));
+                }
+                '}' => return Err("unmatched `}`, write `}}` for a literal brace"),
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            segments.push(Segment::Text(text));
+        }
+        Ok(Template(segments))
+    }
+
+    fn write(&self, out: &mut impl Write, args: &[(&str, &dyn Display)]) -> fmt::Result {
+        for segment in self.0.iter() {
+            match segment {
+                Segment::Text(text) => out.write_str(text)?,
+                Segment::Arg(name) => match args.iter().find(|(arg, _)| arg == name) {
+                    Some((_, value)) => write!(out, "{}", value)?,
+                    None => write!(out, "{{{}}}", name)?,
+                },
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub message: &'static str,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// The messages of one locale.
+#[derive(Clone, Debug)]
+pub struct Catalog {
+    locale: Str,
+    messages: HashMap<String, Template>,
+}
+
+impl Catalog {
+    pub fn parse(locale: impl Into<Str>, source: &str) -> Result<Self, ParseError> {
+        let mut messages = HashMap::new();
+        for (index, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |message| ParseError {
+                line: index + 1,
+                message,
+            };
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| error("expected `key = value`"))?;
+            let template = Template::parse(value.trim()).map_err(error)?;
+            messages.insert(key.trim().to_string(), template);
+        }
+        Ok(Catalog {
+            locale: locale.into(),
+            messages,
+        })
+    }
+
+    pub fn locale(&self) -> &str {
+        &self.locale
+    }
+
+    fn find(&self, key: &str, count: Option<i64>) -> Option<&Template> {
+        let count = match count {
+            Some(count) => count,
+            None => return self.messages.get(key),
+        };
+        let plural = Plural::of(&self.locale, count.unsigned_abs());
+        // an exact zero reads better in many languages, use it when the catalog has one
+        let exact = (count == 0).then(|| format!("{}.zero", key));
+        exact
+            .into_iter()
+            .chain(Some(format!("{}.{}", key, plural.suffix())))
+            .chain(Some(format!("{}.other", key)))
+            .find_map(|key| self.messages.get(&key))
+            .or_else(|| self.messages.get(key))
+    }
+}
+
+struct I18n {
+    catalogs: HashMap<Str, Catalog>,
+    locale: Str,
+    fallback: Str,
+}
+
+thread_local! {
+    static I18N: RefCell<I18n> = RefCell::new(I18n {
+        catalogs: HashMap::new(),
+        locale: "en".into(),
+        fallback: "en".into(),
+    });
+    static DEPENDENTS: RefCell<Dependents> = RefCell::new(Dependents::default());
+}
+
+/// Make a catalog available, replacing any catalog of the same locale.
+///
+/// Containers that translated something are invalidated but only redrawn on the next render, send
+/// `AddCatalog` or `EntryMessage::AddCatalog` to render right away.
+pub fn add_catalog(catalog: Catalog) {
+    I18N.with(|i18n| {
+        i18n.borrow_mut()
+            .catalogs
+            .insert(catalog.locale.clone(), catalog)
+    });
+    DEPENDENTS.with(|dependents| dependents.borrow_mut().invalidate());
+}
+
+/// The locale used for keys missing from the current one.
+pub fn set_fallback(locale: impl Into<Str>) {
+    I18N.with(|i18n| i18n.borrow_mut().fallback = locale.into());
+}
+
+pub fn locale() -> Str {
+    I18N.with(|i18n| i18n.borrow().locale.clone())
+}
+
+/// Switch locales and invalidate every container that translated something.
+pub fn set_locale(locale: impl Into<Str>) {
+    let locale = locale.into();
+    #[cfg(target_arch = "wasm32")]
+    {
+        let root = web_sys::window()
+            .and_then(|window| window.document())
+            .and_then(|document| document.document_element());
+        if let Some(root) = root {
+            let _ = root.set_attribute("lang", &locale);
+        }
+    }
+    I18N.with(|i18n| i18n.borrow_mut().locale = locale);
+    DEPENDENTS.with(|dependents| dependents.borrow_mut().invalidate());
+}
+
+/// Translate `key` into the bump, missing keys render as themselves.
+///
+/// With a `count` the plural form for the current locale is picked by its magnitude, and `{count}`
+/// can be used in the message.
+pub fn translate<'a>(
+    bump: &'a Bump,
+    key: &str,
+    count: Option<i64>,
+    args: &[(&str, &dyn Display)],
+) -> &'a str {
+    DEPENDENTS.with(|dependents| dependents.borrow_mut().track());
+    let mut out = bumpalo::collections::String::new_in(bump);
+    I18N.with(|i18n| {
+        let i18n = i18n.borrow();
+        let template = [&i18n.locale, &i18n.fallback]
+            .iter()
+            .filter_map(|locale| i18n.catalogs.get(*locale))
+            .find_map(|catalog| catalog.find(key, count));
+        match template {
+            Some(template) => {
+                let count_arg = count.as_ref().map(|count| ("count", count as &dyn Display));
+                let args: Vec<_> = args.iter().copied().chain(count_arg).collect();
+                let _ = template.write(&mut out, &args);
+            }
+            None => {
+                log::warn!("missing translation for `{}` in {}", key, i18n.locale);
+                out.push_str(key);
+            }
+        }
+    });
+    out.into_bump_str()
+}
+
+/// Convert any integer `count` of `t!`, saturating at the bounds of `i64`.
+#[doc(hidden)]
+pub fn clamp_count<N>(count: N) -> i64
+where
+    N: TryInto<i64> + PartialOrd + Default + Copy,
+{
+    count.try_into().unwrap_or(if count < N::default() {
+        i64::MIN
+    } else {
+        i64::MAX
+    })
+}
+
+/// Translate a key with arguments, a leading `count` argument picks the plural form.
+///
+/// `count` takes any integer, values beyond `i64` are clamped to `i64::MIN` or `i64::MAX`.
+///
+/// `t!(bump, "greeting", name = target.name)`
+#[macro_export]
+macro_rules! t {
+    ($bump:expr, $key:expr $(,)?) => {
+        $crate::i18n::translate($bump, $key, ::std::option::Option::None, &[])
+    };
+    ($bump:expr, $key:expr, count = $count:expr $(, $name:ident = $value:expr)* $(,)?) => {
+        $crate::i18n::translate(
+            $bump,
+            $key,
+            ::std::option::Option::Some($crate::i18n::clamp_count($count)),
+            &[$((stringify!($name), &$value as &dyn ::std::fmt::Display)),*],
+        )
+    };
+    ($bump:expr, $key:expr $(, $name:ident = $value:expr)+ $(,)?) => {
+        $crate::i18n::translate(
+            $bump,
+            $key,
+            ::std::option::Option::None,
+            &[$((stringify!($name), &$value as &dyn ::std::fmt::Display)),+],
+        )
+    };
+}
+
+/// Switch the locale from any container, the container rerenders and so do all dependents.
+pub struct SetLocale<T> {
+    locale: Str,
+    target: PhantomData<fn(&mut T)>,
+}
+
+impl<T> SetLocale<T> {
+    pub fn new(locale: impl Into<Str>) -> Self {
+        SetLocale {
+            locale: locale.into(),
+            target: PhantomData,
+        }
+    }
+}
+
+impl<T> Messenger for SetLocale<T> {
+    type Target = T;
+
+    fn update(
+        self: Box<Self>,
+        _target: &mut Self::Target,
+        _sender: &MessageSender<Self::Target>,
+        _render_tx: &Sender<((), oneshot::Sender<()>)>,
+    ) -> bool {
+        set_locale(self.locale);
+        true
+    }
+}
+
+/// Add a catalog from any container, the container rerenders and so do all dependents.
+pub struct AddCatalog<T> {
+    catalog: Catalog,
+    target: PhantomData<fn(&mut T)>,
+}
+
+impl<T> AddCatalog<T> {
+    pub fn new(catalog: Catalog) -> Self {
+        AddCatalog {
+            catalog,
+            target: PhantomData,
+        }
+    }
+}
+
+impl<T> Messenger for AddCatalog<T> {
+    type Target = T;
+
+    fn update(
+        self: Box<Self>,
+        _target: &mut Self::Target,
+        _sender: &MessageSender<Self::Target>,
+        _render_tx: &Sender<((), oneshot::Sender<()>)>,
+    ) -> bool {
+        add_catalog(self.catalog);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EN: &str = "
+        # english
+        greeting = Hello, {name}!
+        inbox.one = {count} new message
+        inbox.other = {count} new messages
+        braces = {{literal}}
+    ";
+
+    const RU: &str = "
+        greeting = Привет, {name}!
+        inbox.one = {count} новое сообщение
+        inbox.few = {count} новых сообщения
+        inbox.many = {count} новых сообщений
+    ";
+
+    #[test]
+    fn plural_rules() {
+        assert_eq!(Plural::of("en-US", 1), Plural::One);
+        assert_eq!(Plural::of("fr", 0), Plural::One);
+        assert_eq!(Plural::of("ru", 21), Plural::One);
+        assert_eq!(Plural::of("ru", 12), Plural::Many);
+        assert_eq!(Plural::of("pl", 24), Plural::Few);
+        assert_eq!(Plural::of("ja", 1), Plural::Other);
+        assert_eq!(Plural::of("tr", 1), Plural::One);
+        assert_eq!(Plural::of("tr", 2), Plural::Other);
+    }
+
+    #[test]
+    fn catalog_errors() {
+        assert_eq!(
+            Catalog::parse("en", "ok = fine\nbroken").unwrap_err(),
+            ParseError {
+                line: 2,
+                message: "expected `key = value`"
+            }
+        );
+        assert!(Catalog::parse("en", "a = {name").is_err());
+    }
+
+    #[test]
+    fn translates_into_bump() {
+        let bump = Bump::new();
+        let (sender, _) = mpsc::unbounded();
+        let (render_tx, _) = mpsc::unbounded();
+        add_catalog(Catalog::parse("en", EN).unwrap());
+        let ru = Catalog::parse("ru", RU).unwrap();
+        assert!(Box::new(AddCatalog::new(ru)).update(&mut (), &sender, &render_tx));
+
+        assert_eq!(t!(&bump, "greeting", name = "Ferris"), "Hello, Ferris!");
+        assert_eq!(t!(&bump, "inbox", count = 1), "1 new message");
+        assert_eq!(t!(&bump, "inbox", count = -1), "-1 new message");
+        assert_eq!(t!(&bump, "inbox", count = 2usize), "2 new messages");
+        assert_eq!(
+            t!(&bump, "inbox", count = i128::MIN),
+            format!("{} new messages", i64::MIN)
+        );
+        assert_eq!(
+            t!(&bump, "inbox", count = u64::MAX),
+            format!("{} new messages", i64::MAX)
+        );
+        assert_eq!(t!(&bump, "braces"), "{literal}");
+        assert_eq!(t!(&bump, "missing.key"), "missing.key");
+
+        Box::new(SetLocale::new("ru")).update(&mut (), &sender, &render_tx);
+        assert_eq!(locale(), "ru");
+        assert_eq!(t!(&bump, "inbox", count = 3), "3 новых сообщения");
+        assert_eq!(t!(&bump, "inbox", count = 11), "11 новых сообщений");
+        // falls back to english
+        assert_eq!(t!(&bump, "braces"), "{literal}");
+    }
+}
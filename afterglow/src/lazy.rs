@@ -0,0 +1,302 @@
+//! Create child containers once they scroll into view.
+//!
+//! Keep a `Lazy` in the parent's model instead of a `Container` and render it the same way. Until
+//! its element intersects the viewport only a placeholder is rendered. Once it does the child
+//! container is created, which runs `LifeCycle::new` and `mounted` and starts its message loop,
+//! and the parent rerenders to show it.
+//!
+//! ```ignore
+//! struct Dashboard {
+//!     chart: Lazy<Chart>,
+//! }
+//!
+//! impl LifeCycle for Dashboard {
+//!     fn new(render_tx: Sender<((), oneshot::Sender<()>)>) -> Self {
+//!         Dashboard {
+//!             chart: Lazy::new::<ChartView>(render_tx).placeholder_height(320.0),
+//!         }
+//!     }
+//! }
+//!
+//! // in the dashboard's renderer
+//! target.chart.render(ctx)
+//! ```
+
+use crate::prelude::*;
+use std::cell::{Cell, Ref, RefCell};
+use std::rc::Rc;
+
+/// What happens to a created child once it leaves the viewport.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OnLeave {
+    /// Keep rendering it as usual.
+    #[default]
+    Keep,
+    /// Keep its model and message loop, but render the placeholder until it's visible again.
+    Freeze,
+    /// Drop the container, a new one is created when it's visible again.
+    Destroy,
+}
+
+type Create<T> = Box<dyn Fn(Sender<((), oneshot::Sender<()>)>) -> Container<T>>;
+
+/// Visibility and the child, shared with the observer's callback.
+struct Visibility<T: LifeCycle> {
+    visible: Cell<bool>,
+    #[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+    observed: RefCell<Option<web_sys::Element>>,
+    invalidator: RefCell<Option<Invalidator>>,
+    render_tx: Sender<((), oneshot::Sender<()>)>,
+    create: Create<T>,
+    child: RefCell<Option<Container<T>>>,
+    on_leave: Cell<OnLeave>,
+}
+
+impl<T: LifeCycle> Visibility<T> {
+    /// Record a change, create or drop the child and rerender the container that rendered the
+    /// `Lazy`.
+    fn set(&self, visible: bool) {
+        if self.visible.replace(visible) == visible {
+            return;
+        }
+        self.sync();
+        if let Some(invalidator) = self.invalidator.borrow().as_ref() {
+            invalidator.invalidate();
+        }
+        let (tx, _) = oneshot::channel();
+        let _ = self.render_tx.unbounded_send(((), tx));
+    }
+
+    /// Create or drop the child to match the visibility.
+    fn sync(&self) {
+        let mut child = self.child.borrow_mut();
+        match (self.visible.get(), child.is_some()) {
+            (true, false) => {
+                *child = Some((self.create)(self.render_tx.clone()));
+            }
+            (false, true) if self.on_leave.get() == OnLeave::Destroy => {
+                child.take();
+            }
+            _ => {}
+        }
+    }
+}
+
+type Observer = (
+    web_sys::IntersectionObserver,
+    Closure<dyn FnMut(js_sys::Array)>,
+);
+
+/// A child container created the first time it becomes visible.
+pub struct Lazy<T: LifeCycle> {
+    placeholder_height: f64,
+    root_margin: &'static str,
+    visibility: Rc<Visibility<T>>,
+    observer: RefCell<Option<Observer>>,
+    node_ref: NodeRef,
+}
+
+impl<T: LifeCycle + 'static> Lazy<T> {
+    /// Create the child from `T::new` and a default `R` once visible.
+    pub fn new<R: Renderer<Target = T, Data = T> + Default + 'static>(
+        render_tx: Sender<((), oneshot::Sender<()>)>,
+    ) -> Self {
+        Lazy::with(render_tx, |render_tx| {
+            Container::new(T::new(render_tx.clone()), Box::new(R::default()), render_tx)
+        })
+    }
+
+    /// Create the child with `create` once visible.
+    pub fn with(
+        render_tx: Sender<((), oneshot::Sender<()>)>,
+        create: impl Fn(Sender<((), oneshot::Sender<()>)>) -> Container<T> + 'static,
+    ) -> Self {
+        Lazy {
+            placeholder_height: 0.0,
+            root_margin: "0px",
+            visibility: Rc::new(Visibility {
+                visible: Cell::new(false),
+                observed: RefCell::new(None),
+                invalidator: RefCell::new(None),
+                render_tx,
+                create: Box::new(create),
+                child: RefCell::new(None),
+                on_leave: Cell::new(OnLeave::default()),
+            }),
+            observer: RefCell::new(None),
+            node_ref: NodeRef::new(),
+        }
+    }
+
+    pub fn on_leave(self, on_leave: OnLeave) -> Self {
+        self.visibility.on_leave.set(on_leave);
+        self
+    }
+
+    /// Height of the placeholder in pixels, so the page doesn't jump once the child renders.
+    pub fn placeholder_height(mut self, height: f64) -> Self {
+        self.placeholder_height = height;
+        self
+    }
+
+    /// Grow the viewport used for the check, e.g. `"200px"` creates children a bit early.
+    pub fn root_margin(mut self, margin: &'static str) -> Self {
+        self.root_margin = margin;
+        self
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visibility.visible.get()
+    }
+
+    /// Override what the observer reported, e.g. to reveal the child before it's scrolled to.
+    pub fn set_visible(&self, visible: bool) {
+        self.visibility.set(visible);
+    }
+
+    /// The child container, if it's been created.
+    pub fn child(&self) -> Option<Ref<'_, Container<T>>> {
+        Ref::filter_map(self.visibility.child.borrow(), Option::as_ref).ok()
+    }
+
+    /// Whether the child is rendered instead of the placeholder.
+    fn shown(&self) -> bool {
+        let keep = self.visibility.on_leave.get() == OnLeave::Keep;
+        self.child().is_some() && (self.is_visible() || keep)
+    }
+
+    /// The placeholder until the child is created, rendering never creates or drops it.
+    pub fn render<'a>(&self, ctx: &mut RenderContext<'a>) -> Node<'a> {
+        self.visibility.invalidator.replace(current_invalidator());
+        self.observe();
+
+        let bump = ctx.bump;
+        let wrapper = dodrio::builder::div(bump).attr(
+            NodeRef::ATTR,
+            dodrio::bumpalo::collections::String::from_str_in(&self.node_ref.value(), bump)
+                .into_bump_str(),
+        );
+        if self.shown() {
+            if let Some(child) = self.child() {
+                return wrapper.children(child.render_children(ctx)).finish();
+            }
+        }
+        wrapper
+            .attr(
+                "style",
+                bf!(in bump, "min-height: {}px", self.placeholder_height).into_bump_str(),
+            )
+            .finish()
+    }
+
+    /// Watch the wrapper element once dodrio patched it into the document.
+    #[cfg(target_arch = "wasm32")]
+    fn observe(&self) {
+        let mut observer = self.observer.borrow_mut();
+        if observer.is_none() {
+            let visibility = Rc::downgrade(&self.visibility);
+            let callback = Closure::wrap(Box::new(move |entries: js_sys::Array| {
+                let visible = entries.iter().any(|entry| {
+                    entry
+                        .dyn_into::<web_sys::IntersectionObserverEntry>()
+                        .is_ok_and(|entry| entry.is_intersecting())
+                });
+                if let Some(visibility) = visibility.upgrade() {
+                    visibility.set(visible);
+                }
+            }) as Box<dyn FnMut(js_sys::Array)>);
+            let options = web_sys::IntersectionObserverInit::new();
+            options.set_root_margin(self.root_margin);
+            *observer = web_sys::IntersectionObserver::new_with_options(
+                callback.as_ref().unchecked_ref(),
+                &options,
+            )
+            .ok()
+            .map(|observer| (observer, callback));
+        }
+        let observer = match observer.as_ref() {
+            Some((observer, _)) => observer.clone(),
+            None => return,
+        };
+        let node_ref = self.node_ref.clone();
+        let visibility = Rc::downgrade(&self.visibility);
+        spawn_local(async move {
+            let (element, visibility) = match (node_ref.get(), visibility.upgrade()) {
+                (Some(element), Some(visibility)) => (element, visibility),
+                _ => return,
+            };
+            let mut observed = visibility.observed.borrow_mut();
+            if observed.as_ref() != Some(&element) {
+                if let Some(previous) = observed.replace(element.clone()) {
+                    observer.unobserve(&previous);
+                }
+                observer.observe(&element);
+            }
+        });
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn observe(&self) {}
+}
+
+impl<T: LifeCycle> Drop for Lazy<T> {
+    fn drop(&mut self) {
+        if let Some((observer, _)) = self.observer.get_mut().take() {
+            observer.disconnect();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spawner::NativeSpawner;
+
+    struct Widget;
+
+    impl LifeCycle for Widget {
+        fn new(_render_tx: Sender<((), oneshot::Sender<()>)>) -> Self {
+            Widget
+        }
+    }
+
+    #[derive(Default)]
+    struct WidgetView;
+
+    impl Renderer for WidgetView {
+        type Target = Widget;
+        type Data = Widget;
+
+        fn view<'a>(
+            &self,
+            _target: &Self::Target,
+            _ctx: &mut RenderContext<'a>,
+            _sender: &MessageSender<Self::Data>,
+        ) -> Node<'a> {
+            text("widget")
+        }
+    }
+
+    #[test]
+    fn created_when_visible() {
+        let spawner = NativeSpawner::current();
+        let (render_tx, mut render_rx) = mpsc::unbounded();
+        let lazy = Lazy::<Widget>::new::<WidgetView>(render_tx).on_leave(OnLeave::Destroy);
+        assert!(lazy.child().is_none());
+
+        lazy.set_visible(true);
+        assert!(lazy.child().is_some() && lazy.shown());
+        assert!(render_rx.try_recv().is_ok());
+
+        lazy.set_visible(false);
+        assert!(lazy.child().is_none());
+        spawner.run_until_stalled();
+
+        let frozen =
+            Lazy::<Widget>::new::<WidgetView>(mpsc::unbounded().0).on_leave(OnLeave::Freeze);
+        frozen.set_visible(true);
+        frozen.set_visible(false);
+        assert!(frozen.child().is_some() && !frozen.shown());
+        spawner.run_until_stalled();
+    }
+}
@@ -1,8 +1,32 @@
+extern crate self as afterglow;
+
+pub mod a11y;
+pub mod backend;
 pub mod bus;
 pub mod container;
+pub mod events;
+pub mod forms;
+pub mod head;
+pub mod html;
+pub mod i18n;
+pub mod lazy;
+pub mod markup;
 pub mod messenger;
+pub mod node_ref;
+pub mod portal;
 pub mod prelude;
 pub mod renderer;
+pub mod responsive;
+pub mod shortcuts;
+pub mod spawner;
+pub mod state;
+pub mod style;
+pub mod theme;
+pub mod transition;
+pub mod virtual_list;
+pub mod vnode;
+
+pub use dodrio;
 
 #[cfg(test)]
 mod tests {
@@ -10,7 +34,6 @@ mod tests {
     wasm_bindgen_test_configure!(run_in_browser);
 
     pub fn init_test() {
-        let _ = femme::with_level(log::LevelFilter::Info);
+        femme::with_level(log::LevelFilter::Info);
     }
 }
-
@@ -0,0 +1,606 @@
+//! Rich text from html strings, e.g. rendered markdown or content from a CMS.
+//!
+//! The string is parsed into nodes that go through the vdom like the rest of the view. Untrusted
+//! input is cleaned by a `Sanitizer` first, which keeps a small set of formatting tags and drops
+//! scripts, event handlers, styles and `javascript:` urls. Parse once when the content changes
+//! and keep the `Markup` in the model:
+//!
+//! ```ignore
+//! // in `Messenger::update`
+//! target.body = Markup::sanitize(&post.body_html);
+//!
+//! // in the renderer
+//! html!(bump, <article>{ &target.body }</article>)
+//! ```
+//!
+//! Input that's known to be safe, e.g. produced at build time, can skip the sanitizer with
+//! `Markup::unsafe_trusted`.
+
+use crate::html::IntoNodes;
+use crate::prelude::*;
+use crate::vnode::{el, VNode};
+use dodrio::builder::ElementBuilder;
+use dodrio::bumpalo::{self, Bump};
+use std::collections::HashMap;
+
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+
+/// Elements whose content isn't markup.
+const RAW_TEXT: &[&str] = &["script", "style", "textarea", "title"];
+
+const CLOSES_PARAGRAPH: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "details",
+    "div",
+    "dl",
+    "fieldset",
+    "figure",
+    "footer",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hr",
+    "main",
+    "nav",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "table",
+    "ul",
+];
+
+const SVG_NAMESPACE: &str = "http://www.w3.org/2000/svg";
+
+#[derive(Clone, Debug, PartialEq)]
+enum MarkupNode {
+    Element {
+        tag: String,
+        attrs: Vec<(String, String)>,
+        children: Vec<MarkupNode>,
+    },
+    Text(String),
+}
+
+/// Parsed html, ready to be rendered.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Markup(Vec<MarkupNode>);
+
+impl Markup {
+    /// Parse untrusted html and clean it with the default `Sanitizer`.
+    pub fn sanitize(html: &str) -> Self {
+        Markup::sanitize_with(html, &Sanitizer::default())
+    }
+
+    pub fn sanitize_with(html: &str, sanitizer: &Sanitizer) -> Self {
+        Markup(sanitizer.clean(parse(html)))
+    }
+
+    /// Parse html as is, scripts and event handlers included.
+    ///
+    /// Only for html that can't contain user input, anything else should go through `sanitize`.
+    pub fn unsafe_trusted(html: &str) -> Self {
+        Markup(parse(html))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Backend independent nodes, for renderers implementing `View`.
+    pub fn to_vnode(&self) -> VNode {
+        fn convert(node: &MarkupNode, svg: bool) -> VNode {
+            match node {
+                MarkupNode::Text(content) => crate::vnode::text(content.clone()),
+                MarkupNode::Element {
+                    tag,
+                    attrs,
+                    children,
+                } => {
+                    let svg = svg || tag == "svg";
+                    let mut element = el(tag.clone());
+                    if svg {
+                        element = element.namespace(SVG_NAMESPACE);
+                    }
+                    for (name, value) in attrs {
+                        element = element.attr(name.clone(), value.clone());
+                    }
+                    element
+                        .children(children.iter().map(|child| convert(child, svg)))
+                        .finish()
+                }
+            }
+        }
+        crate::vnode::fragment(self.0.iter().map(|node| convert(node, false)))
+    }
+}
+
+fn build<'a>(bump: &'a Bump, node: &MarkupNode, svg: bool) -> Node<'a> {
+    let alloc =
+        |value: &str| bumpalo::collections::String::from_str_in(value, bump).into_bump_str();
+    match node {
+        MarkupNode::Text(content) => text(alloc(content)),
+        MarkupNode::Element {
+            tag,
+            attrs,
+            children,
+        } => {
+            let svg = svg || tag == "svg";
+            let mut element = ElementBuilder::new(bump, alloc(tag));
+            if svg {
+                element = element.namespace(Some(SVG_NAMESPACE));
+            }
+            for (name, value) in attrs {
+                element = element.attr(alloc(name), alloc(value));
+            }
+            let children = bumpalo::collections::Vec::from_iter_in(
+                children.iter().map(|child| build(bump, child, svg)),
+                bump,
+            );
+            element.children(children).finish()
+        }
+    }
+}
+
+impl<'a> IntoNodes<'a> for &Markup {
+    fn into_nodes(self, bump: &'a Bump, nodes: &mut bumpalo::collections::Vec<'a, Node<'a>>) {
+        nodes.extend(self.0.iter().map(|node| build(bump, node, false)));
+    }
+}
+
+impl<'a> IntoNodes<'a> for Markup {
+    fn into_nodes(self, bump: &'a Bump, nodes: &mut bumpalo::collections::Vec<'a, Node<'a>>) {
+        (&self).into_nodes(bump, nodes);
+    }
+}
+
+/// Which tags and attributes survive `Markup::sanitize`.
+///
+/// Tags that aren't allowed are replaced by their content, except for scripts, styles and other
+/// embedded content which are dropped entirely. Event handlers and `style` are never kept, urls
+/// must be relative or use `http`, `https` or `mailto`.
+#[derive(Clone, Debug)]
+pub struct Sanitizer {
+    tags: HashMap<&'static str, Vec<&'static str>>,
+    global: Vec<&'static str>,
+}
+
+const DROP_WITH_CONTENT: &[&str] = &[
+    "script", "style", "iframe", "object", "embed", "template", "noscript", "textarea", "select",
+    "title", "svg", "math", "frame", "frameset", "applet", "base", "link", "meta",
+];
+
+const URL_ATTRS: &[&str] = &["href", "src", "cite", "action", "formaction", "poster"];
+
+impl Default for Sanitizer {
+    fn default() -> Self {
+        let mut sanitizer = Sanitizer {
+            tags: HashMap::new(),
+            global: vec!["title", "lang", "dir"],
+        };
+        let formatting =
+            "abbr b blockquote br caption code dd del details div dl dt em figcaption \
+             figure h1 h2 h3 h4 h5 h6 hr i ins kbd li mark p pre q s small span \
+             strong sub summary sup table tbody tfoot thead tr u ul";
+        for tag in formatting.split_whitespace() {
+            sanitizer = sanitizer.allow(tag, &[]);
+        }
+        sanitizer
+            .allow("a", &["href", "rel"])
+            .allow("img", &["src", "alt", "width", "height"])
+            .allow("ol", &["start", "reversed"])
+            .allow("td", &["colspan", "rowspan"])
+            .allow("th", &["colspan", "rowspan", "scope"])
+            .allow("col", &["span"])
+            .allow("colgroup", &["span"])
+            .allow("time", &["datetime"])
+    }
+}
+
+impl Sanitizer {
+    /// Keep `tag` along with the given attributes.
+    pub fn allow(mut self, tag: &'static str, attrs: &[&'static str]) -> Self {
+        self.tags.entry(tag).or_default().extend_from_slice(attrs);
+        self
+    }
+
+    /// Keep an attribute on every allowed tag, e.g. `class`.
+    pub fn allow_global(mut self, attr: &'static str) -> Self {
+        self.global.push(attr);
+        self
+    }
+
+    fn clean(&self, nodes: Vec<MarkupNode>) -> Vec<MarkupNode> {
+        let mut cleaned = vec![];
+        for node in nodes {
+            match node {
+                MarkupNode::Text(_) => cleaned.push(node),
+                MarkupNode::Element { tag, .. } if DROP_WITH_CONTENT.contains(&tag.as_str()) => {}
+                MarkupNode::Element {
+                    tag,
+                    attrs,
+                    children,
+                } => match self.tags.get(tag.as_str()) {
+                    Some(allowed) => {
+                        let attrs = attrs
+                            .into_iter()
+                            .filter(|(name, value)| {
+                                let name = name.as_str();
+                                !name.starts_with("on")
+                                    && name != "style"
+                                    && (allowed.contains(&name) || self.global.contains(&name))
+                                    && (!URL_ATTRS.contains(&name) || safe_url(value))
+                            })
+                            .collect();
+                        cleaned.push(MarkupNode::Element {
+                            tag,
+                            attrs,
+                            children: self.clean(children),
+                        });
+                    }
+                    None => cleaned.extend(self.clean(children)),
+                },
+            }
+        }
+        cleaned
+    }
+}
+
+/// Relative urls and a few harmless schemes.
+fn safe_url(url: &str) -> bool {
+    // browsers ignore whitespace and control characters inside the scheme
+    let url: String = url
+        .chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .collect::<String>()
+        .to_ascii_lowercase();
+    match url.find(':') {
+        Some(colon) if !url[..colon].contains(['/', '?', '#']) => {
+            matches!(&url[..colon], "http" | "https" | "mailto")
+        }
+        _ => true,
+    }
+}
+
+/// Parse html leniently, the way browsers recover from sloppy markup is roughly followed.
+fn parse(html: &str) -> Vec<MarkupNode> {
+    let mut parser = Parser {
+        src: html,
+        pos: 0,
+        stack: vec![],
+        root: vec![],
+    };
+    parser.run();
+    parser.root
+}
+
+/// An element's tag, attributes and children parsed so far.
+type Open = (String, Vec<(String, String)>, Vec<MarkupNode>);
+
+struct Parser<'s> {
+    src: &'s str,
+    pos: usize,
+    stack: Vec<Open>,
+    root: Vec<MarkupNode>,
+}
+
+impl<'s> Parser<'s> {
+    fn rest(&self) -> &'s str {
+        &self.src[self.pos..]
+    }
+
+    fn push(&mut self, node: MarkupNode) {
+        match self.stack.last_mut() {
+            Some((_, _, children)) => children.push(node),
+            None => self.root.push(node),
+        }
+    }
+
+    fn close_top(&mut self) {
+        if let Some((tag, attrs, children)) = self.stack.pop() {
+            self.push(MarkupNode::Element {
+                tag,
+                attrs,
+                children,
+            });
+        }
+    }
+
+    fn run(&mut self) {
+        while !self.rest().is_empty() {
+            let rest = self.rest();
+            if rest.starts_with("<!--") {
+                self.pos += rest.find("-->").map_or(rest.len(), |end| end + 3);
+            } else if rest.starts_with("</") {
+                self.closing_tag();
+            } else if rest.starts_with("<!") || rest.starts_with("<?") {
+                self.pos += rest.find('>').map_or(rest.len(), |end| end + 1);
+            } else if rest.starts_with('<')
+                && rest[1..].starts_with(|c: char| c.is_ascii_alphabetic())
+            {
+                self.opening_tag();
+            } else {
+                // a `<` that doesn't start a tag is text
+                let skip = rest.chars().next().map_or(0, char::len_utf8);
+                let end = rest[skip..].find('<').map_or(rest.len(), |end| end + skip);
+                self.text(&rest[..end]);
+                self.pos += end;
+            }
+        }
+        while !self.stack.is_empty() {
+            self.close_top();
+        }
+    }
+
+    fn text(&mut self, content: &str) {
+        let content = decode(content);
+        if let Some(MarkupNode::Text(previous)) = self
+            .stack
+            .last_mut()
+            .map_or(&mut self.root, |(_, _, children)| children)
+            .last_mut()
+        {
+            previous.push_str(&content);
+            return;
+        }
+        self.push(MarkupNode::Text(content));
+    }
+
+    fn name(&mut self) -> String {
+        let rest = self.rest();
+        let end = rest
+            .find(|c: char| c.is_whitespace() || c == '/' || c == '>' || c == '=')
+            .unwrap_or(rest.len());
+        self.pos += end;
+        rest[..end].to_ascii_lowercase()
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn closing_tag(&mut self) {
+        self.pos += 2;
+        let tag = self.name();
+        let rest = self.rest();
+        self.pos += rest.find('>').map_or(rest.len(), |end| end + 1);
+        // close everything opened inside the element, stray closing tags are ignored
+        if let Some(index) = self.stack.iter().rposition(|(open, _, _)| *open == tag) {
+            while self.stack.len() > index {
+                self.close_top();
+            }
+        }
+    }
+
+    fn opening_tag(&mut self) {
+        self.pos += 1;
+        let tag = self.name();
+        let mut attrs: Vec<(String, String)> = vec![];
+        let mut self_closing = false;
+        loop {
+            self.skip_whitespace();
+            let rest = self.rest();
+            if rest.is_empty() {
+                break;
+            } else if rest.starts_with("/>") {
+                self.pos += 2;
+                self_closing = true;
+                break;
+            } else if rest.starts_with('>') {
+                self.pos += 1;
+                break;
+            } else if rest.starts_with('/') {
+                self.pos += 1;
+                continue;
+            }
+            let name = self.name();
+            if name.is_empty() {
+                // a stray `=`
+                self.pos += 1;
+                continue;
+            }
+            self.skip_whitespace();
+            let value = if self.rest().starts_with('=') {
+                self.pos += 1;
+                self.skip_whitespace();
+                self.attr_value()
+            } else {
+                String::new()
+            };
+            if !attrs.iter().any(|(known, _)| *known == name) {
+                attrs.push((name, value));
+            }
+        }
+
+        // elements that can't nest in themselves close the previous one, blocks close paragraphs
+        let (closes, scope): (&[&str], &[&str]) = match tag.as_str() {
+            "li" => (&["li"], &["ul", "ol"]),
+            "dt" | "dd" => (&["dt", "dd"], &["dl"]),
+            "tr" => (&["tr"], &["table", "thead", "tbody", "tfoot"]),
+            "td" | "th" => (&["td", "th"], &["tr", "table"]),
+            "option" => (&["option"], &["select"]),
+            tag if CLOSES_PARAGRAPH.contains(&tag) => (&["p"], &["td", "th", "button"]),
+            _ => (&[], &[]),
+        };
+        let open = self
+            .stack
+            .iter()
+            .rev()
+            .map(|(open, _, _)| open.as_str())
+            .take_while(|open| !scope.contains(open))
+            .position(|open| closes.contains(&open));
+        if let Some(depth) = open {
+            for _ in 0..=depth {
+                self.close_top();
+            }
+        }
+
+        if self_closing || VOID_ELEMENTS.contains(&tag.as_str()) {
+            self.push(MarkupNode::Element {
+                tag,
+                attrs,
+                children: vec![],
+            });
+        } else if RAW_TEXT.contains(&tag.as_str()) {
+            let rest = self.rest();
+            let closing = format!("</{}", tag);
+            let end = rest
+                .to_ascii_lowercase()
+                .find(&closing)
+                .unwrap_or(rest.len());
+            let content = &rest[..end];
+            self.pos += end;
+            let after = self.rest();
+            self.pos += after.find('>').map_or(after.len(), |end| end + 1);
+            let children = if content.is_empty() {
+                vec![]
+            } else {
+                vec![MarkupNode::Text(content.to_string())]
+            };
+            self.push(MarkupNode::Element {
+                tag,
+                attrs,
+                children,
+            });
+        } else {
+            self.stack.push((tag, attrs, vec![]));
+        }
+    }
+
+    fn attr_value(&mut self) -> String {
+        let rest = self.rest();
+        let (value, consumed) = match rest.chars().next() {
+            Some(quote @ ('"' | '\'')) => match rest[1..].find(quote) {
+                Some(end) => (&rest[1..end + 1], end + 2),
+                None => (&rest[1..], rest.len()),
+            },
+            _ => {
+                let end = rest
+                    .find(|c: char| c.is_whitespace() || c == '>')
+                    .unwrap_or(rest.len());
+                (&rest[..end], end)
+            }
+        };
+        self.pos += consumed;
+        decode(value)
+    }
+}
+
+/// Replace character references, unknown ones are kept as written.
+fn decode(value: &str) -> String {
+    let mut decoded = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = match rest.find(';') {
+            Some(end) if end <= 10 => end,
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+                continue;
+            }
+        };
+        let entity = &rest[1..end];
+        let character = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some('\u{a0}'),
+            _ => entity
+                .strip_prefix("#x")
+                .or_else(|| entity.strip_prefix("#X"))
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| entity.strip_prefix('#').map(str::parse))
+                .and_then(Result::ok)
+                .and_then(char::from_u32),
+        };
+        match character {
+            Some(character) => {
+                decoded.push(character);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{Backend, Html};
+
+    fn render(markup: Markup) -> String {
+        Html.build(markup.to_vnode())
+    }
+
+    #[test]
+    fn parses_sloppy_html() {
+        let markup = Markup::unsafe_trusted(
+            "<P class=intro>One &amp; <b>two<p>three &#x3C;4&gt;<br>\
+             <!-- gone --><ul><li>a<li>b</ul><img src='x.png' alt=\"x\"/>",
+        );
+        assert_eq!(
+            render(markup),
+            "<p class=\"intro\">One &amp; <b>two</b></p><p>three &lt;4&gt;<br></p>\
+             <ul><li>a</li><li>b</li></ul><img src=\"x.png\" alt=\"x\">"
+        );
+    }
+
+    #[test]
+    fn parses_non_ascii_text() {
+        assert_eq!(
+            render(Markup::sanitize("é<p>ü and ß</p>")),
+            "é<p>ü and ß</p>"
+        );
+    }
+
+    #[test]
+    fn sanitizes_untrusted_html() {
+        let markup = Markup::sanitize(
+            "<h1 onclick=\"steal()\" style=\"color: red\">Title</h1>\
+             <script>alert(1)</script><custom-tag>kept <em>text</em></custom-tag>\
+             <a href=\" JaVa\tScRiPt:alert(1)\">bad</a><a href=\"/posts?a=b:c\" rel=\"nofollow\">ok</a>\
+             <img src=\"https://example.com/a.png\" onerror=\"x()\"><iframe src=\"/\">x</iframe>",
+        );
+        assert_eq!(
+            render(markup),
+            "<h1>Title</h1>kept <em>text</em><a>bad</a>\
+             <a href=\"/posts?a=b:c\" rel=\"nofollow\">ok</a><img src=\"https://example.com/a.png\">"
+        );
+
+        let sanitizer = Sanitizer::default().allow_global("class");
+        let markup = Markup::sanitize_with("<p class=\"lead\" id=\"x\">hi</p>", &sanitizer);
+        assert_eq!(render(markup), "<p class=\"lead\">hi</p>");
+    }
+
+    #[test]
+    fn builds_dodrio_nodes() {
+        let bump = Bump::new();
+        let markup = Markup::unsafe_trusted("<svg viewBox=\"0 0 1 1\"><path d=\"M0 0\"/></svg>");
+        let mut nodes = bumpalo::collections::Vec::new_in(&bump);
+        (&markup).into_nodes(&bump, &mut nodes);
+        let nodes = format!("{:?}", nodes);
+        assert!(nodes.contains("tag_name: \"path\""));
+        assert!(nodes.contains(SVG_NAMESPACE));
+    }
+}
@@ -1,36 +1,50 @@
 use crate::prelude::*;
-use async_std::task;
 use dodrio::{RootRender, VdomWeak};
 
+pub use afterglow_macro::Messenger;
+
 pub type Message<T> = Box<dyn Messenger<Target = T>>;
-pub type MessageSender<T> = Sender<(Message<T>, oneshot::Sender<()>)>;
-pub type MessageReceiver<T> = Receiver<(Message<T>, oneshot::Sender<()>)>;
+/// Notified once the message has been handled, `None` when nobody is waiting for it.
+pub type Ack = Option<oneshot::Sender<()>>;
+pub type MessageSender<T> = Sender<(Message<T>, Ack)>;
+pub type MessageReceiver<T> = Receiver<(Message<T>, Ack)>;
 
 pub trait Messenger {
     type Target;
 
     fn update(
         self: Box<Self>,
-        target: &mut Self::Target,
-        sender: &MessageSender<Self::Target>,
-        render_tx: &Sender<((), oneshot::Sender<()>)>,
+        _target: &mut Self::Target,
+        _sender: &MessageSender<Self::Target>,
+        _render_tx: &Sender<((), oneshot::Sender<()>)>,
     ) -> bool {
         false
     }
 
     /// disptach a msg toward it's target.
-    fn dispatch(self, sender: &MessageSender<Self::Target>) -> task::JoinHandle<()>
+    fn dispatch(self, sender: &MessageSender<Self::Target>) -> JoinHandle<()>
     where
         Self: Sized + 'static,
     {
         let mut sender = sender.clone();
 
-        task::spawn_local(async move {
+        spawn(async move {
             let (tx, rx) = oneshot::channel::<()>();
-            let _ = sender.send((Box::new(self), tx)).await;
+            let _ = sender.send((Box::new(self), Some(tx))).await;
             let _ = rx.await;
         })
     }
+
+    /// queue a msg toward it's target without waiting for it to be handled.
+    ///
+    /// Unlike `dispatch` this doesn't spawn a task or allocate an acknowledgement channel,
+    /// which is what high frequency events like `mousemove` or `input` want.
+    fn send(self, sender: &MessageSender<Self::Target>)
+    where
+        Self: Sized + 'static,
+    {
+        let _ = sender.unbounded_send((Box::new(self), None));
+    }
 }
 
 /// convert a msg into a closure to satisfy dodrio's internal renderer
@@ -44,12 +58,12 @@ where
 {
     let sender = sender.clone();
     move |_, _, event| {
-        let msg = convert(event);
-        spawn_local(msg.dispatch(&sender));
+        convert(event).send(&sender);
     }
 }
 
 #[cfg(test)]
+#[allow(dead_code)]
 mod tests {
     use super::*;
     use futures::channel::mpsc::unbounded;
@@ -78,8 +92,8 @@ mod tests {
         fn update(
             self: Box<Self>,
             target: &mut Self::Target,
-            sender: &MessageSender<Self::Target>,
-            render_tx: &Sender<((), oneshot::Sender<()>)>,
+            _sender: &MessageSender<Self::Target>,
+            _render_tx: &Sender<((), oneshot::Sender<()>)>,
         ) -> bool {
             target.button = !target.button;
             true
@@ -92,11 +106,8 @@ mod tests {
         fn update(
             self: Box<Self>,
             target: &mut Self::Target,
-            sender: &Sender<(
-                Box<dyn Messenger<Target = Self::Target>>,
-                oneshot::Sender<()>,
-            )>,
-            render_tx: &Sender<((), oneshot::Sender<()>)>,
+            _sender: &Sender<(Box<dyn Messenger<Target = Self::Target>>, Ack)>,
+            _render_tx: &Sender<((), oneshot::Sender<()>)>,
         ) -> bool {
             log::info!("not sure what to do, {}", target.button);
             false
@@ -110,14 +121,16 @@ mod tests {
     impl Container<Data> {
         fn start_handling(&self) {
             let (render_tx, _) = unbounded::<((), oneshot::Sender<()>)>();
-            let (tx, mut rx) = unbounded::<(Message<Data>, oneshot::Sender<()>)>();
+            let (tx, mut rx) = unbounded::<(Message<Data>, Ack)>();
             let data = self.data.clone();
             let tx_handle = tx.clone();
             let fut = async move {
                 while let Some((msg, ready)) = rx.next().await {
                     let mut content = data.lock().await;
                     msg.update(&mut content, &tx_handle, &render_tx);
-                    let _ = ready.send(());
+                    if let Some(ready) = ready {
+                        let _ = ready.send(());
+                    }
                     log::info!("content value: {}", content.button);
                 }
             };
@@ -125,4 +138,105 @@ mod tests {
             spawn_local(fut);
         }
     }
+
+    #[derive(Clone, Messenger)]
+    #[messenger(target = Data, bus = BusEvent::Button, receive)]
+    pub enum ButtonMsg {
+        Flip,
+        Set(bool),
+        #[messenger(handler = toggled, context)]
+        Toggled {
+            times: usize,
+        },
+        #[messenger(context)]
+        Navigate {
+            target: String,
+        },
+    }
+
+    /// Shares the target and bus event with `ButtonMsg`, without a conflicting conversion.
+    #[derive(Clone, Messenger)]
+    #[messenger(target = Data, bus = BusEvent::Reset)]
+    pub enum ResetMsg {
+        Reset,
+    }
+
+    #[derive(Clone)]
+    pub enum BusEvent {
+        Button(ButtonMsg),
+        Reset(ResetMsg),
+        Other,
+    }
+
+    impl Data {
+        fn on_flip(&mut self) -> bool {
+            self.button = !self.button;
+            true
+        }
+
+        fn on_reset(&mut self) -> bool {
+            self.on_set(false)
+        }
+
+        fn on_set(&mut self, value: bool) -> bool {
+            let changed = self.button != value;
+            self.button = value;
+            changed
+        }
+
+        fn toggled(
+            &mut self,
+            times: usize,
+            _sender: &MessageSender<Self>,
+            _render_tx: &Sender<((), oneshot::Sender<()>)>,
+        ) -> bool {
+            (0..times).fold(false, |_, _| self.on_flip())
+        }
+
+        fn on_navigate(
+            &mut self,
+            target: String,
+            _sender: &MessageSender<Self>,
+            _render_tx: &Sender<((), oneshot::Sender<()>)>,
+        ) -> bool {
+            self.on_set(target == "on")
+        }
+    }
+
+    #[test]
+    fn derived_messenger_calls_handlers() {
+        let (sender, _) = unbounded();
+        let (render_tx, _) = unbounded();
+        let mut data = Data { button: false };
+
+        let msgs: Vec<Message<Data>> = vec![
+            Box::new(ButtonMsg::Flip),
+            Box::new(ButtonMsg::Set(true)),
+            Box::new(ButtonMsg::Toggled { times: 3 }),
+            Box::new(ButtonMsg::Navigate {
+                target: "on".to_string(),
+            }),
+        ];
+        let renders: Vec<bool> = msgs
+            .into_iter()
+            .map(|msg| msg.update(&mut data, &sender, &render_tx))
+            .collect();
+
+        assert_eq!(renders, vec![true, false, true, true]);
+        assert!(data.button);
+    }
+
+    #[test]
+    fn derived_messenger_converts_bus_events() {
+        let event: BusEvent = ButtonMsg::Flip.into();
+        let msg: Option<Message<Data>> = event.into();
+        assert!(msg.is_some());
+
+        let msg: Option<Message<Data>> = BusEvent::Other.into();
+        assert!(msg.is_none());
+
+        let event: BusEvent = ResetMsg::Reset.into();
+        let msg: Option<Message<Data>> = event.into();
+        assert!(msg.is_none());
+    }
 }
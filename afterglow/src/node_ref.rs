@@ -0,0 +1,135 @@
+//! Reach the DOM element behind a node built by a renderer.
+//!
+//! Keep a `NodeRef` in the model, mark an element with it in the view, then read it from
+//! `LifeCycle::rendererd` or a `Messenger` once dodrio has patched the document:
+//!
+//! ```ignore
+//! html!(bump, <input data-ag-ref={target.search.value()} />)
+//!
+//! // later, e.g. in `rendererd`
+//! if let Some(input) = self.search.cast::<web_sys::HtmlElement>() {
+//!     let _ = input.focus();
+//! }
+//! ```
+
+use crate::prelude::*;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+thread_local! {
+    static NEXT_ID: Cell<u32> = const { Cell::new(0) };
+}
+
+/// A handle to a rendered element, clones refer to the same element.
+#[derive(Clone)]
+pub struct NodeRef(Rc<RefInner>);
+
+struct RefInner {
+    id: u32,
+    element: RefCell<Option<web_sys::Element>>,
+}
+
+impl Default for NodeRef {
+    fn default() -> Self {
+        let id = NEXT_ID.with(|next| next.replace(next.get() + 1));
+        NodeRef(Rc::new(RefInner {
+            id,
+            element: RefCell::new(None),
+        }))
+    }
+}
+
+impl NodeRef {
+    /// The attribute marking the element.
+    pub const ATTR: &'static str = "data-ag-ref";
+
+    pub fn new() -> Self {
+        NodeRef::default()
+    }
+
+    /// Value of the `data-ag-ref` attribute for the element.
+    pub fn value(&self) -> String {
+        format!("ag-ref-{}", self.0.id)
+    }
+
+    /// The element, `None` if it isn't rendered or was removed since.
+    pub fn get(&self) -> Option<web_sys::Element> {
+        let cached = self.0.element.borrow().clone();
+        if let Some(element) = cached {
+            if element.is_connected() {
+                return Some(element);
+            }
+            self.clear();
+        }
+        let element = self.find();
+        self.0.element.replace(element.clone());
+        element
+    }
+
+    /// The element cast into a more specific type.
+    pub fn cast<E: JsCast>(&self) -> Option<E> {
+        self.get().and_then(|element| element.dyn_into::<E>().ok())
+    }
+
+    /// Forget the cached element.
+    pub fn clear(&self) {
+        self.0.element.replace(None);
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn find(&self) -> Option<web_sys::Element> {
+        let selector = format!("[{}=\"{}\"]", NodeRef::ATTR, self.value());
+        web_sys::window()?
+            .document()?
+            .query_selector(&selector)
+            .ok()
+            .flatten()
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn find(&self) -> Option<web_sys::Element> {
+        None
+    }
+}
+
+impl PartialEq for NodeRef {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl std::fmt::Debug for NodeRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("NodeRef").field(&self.value()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[test]
+    fn refs_are_unique() {
+        let first = NodeRef::new();
+        let second = NodeRef::new();
+        assert_ne!(first.value(), second.value());
+        assert_eq!(first.clone(), first);
+        assert!(first.get().is_none());
+    }
+
+    #[wasm_bindgen_test]
+    fn cleared_when_removed() {
+        let node_ref = NodeRef::new();
+        let document = web_sys::window().unwrap().document().unwrap();
+        let element = document.create_element("canvas").unwrap();
+        element
+            .set_attribute(NodeRef::ATTR, &node_ref.value())
+            .unwrap();
+        document.body().unwrap().append_child(&element).unwrap();
+
+        assert!(node_ref.cast::<web_sys::HtmlElement>().is_some());
+        element.remove();
+        assert!(node_ref.get().is_none());
+    }
+}
@@ -0,0 +1,365 @@
+//! Render part of a container somewhere else in the document.
+//!
+//! Modals, tooltips and dropdowns rendered inline get clipped by ancestors with
+//! `overflow: hidden`. A renderer can return portals from `Renderer::portals`, each one renders
+//! the container's model into another element with it's own renderer, and messages from it's
+//! listeners go to the container like any other.
+//!
+//! ```ignore
+//! impl Renderer for PageView {
+//!     fn portals(&self) -> Vec<Portal<Page, Page>> {
+//!         vec![Portal::new(PortalTarget::Id("modal-root"), Box::new(ModalView))
+//!             .when(|page: &Page| page.confirming)]
+//!     }
+//! }
+//! ```
+//!
+//! Portals are mounted while their `when` condition holds, checked when the container is created
+//! and whenever a message changes the model. Mounted portals rerender along with the container and
+//! are removed once the condition fails or the container is dropped. A theme or locale switch that
+//! affects a portal invalidates its container, so both rerender.
+
+use crate::prelude::*;
+use crate::renderer::ContainerView;
+use dodrio::Vdom;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Where a portal is mounted.
+#[derive(Clone)]
+pub enum PortalTarget {
+    /// The document's body.
+    Body,
+    /// The element with the given id.
+    Id(&'static str),
+    Element(web_sys::Element),
+}
+
+type Show<T> = Box<dyn Fn(&T) -> bool>;
+
+pub struct Portal<T, D> {
+    target: PortalTarget,
+    renderer: Render<T, D>,
+    show: Option<Show<T>>,
+}
+
+impl<T, D> Portal<T, D> {
+    pub fn new(target: PortalTarget, renderer: Render<T, D>) -> Self {
+        Portal {
+            target,
+            renderer,
+            show: None,
+        }
+    }
+}
+
+impl<T: 'static, D> Portal<T, D> {
+    /// Only mount the portal while `show` returns true, a portal without condition is always
+    /// mounted. Calling it again adds another condition.
+    pub fn when(mut self, show: impl Fn(&T) -> bool + 'static) -> Self {
+        self.show = Some(match self.show.take() {
+            Some(previous) => Box::new(move |target: &T| previous(target) && show(target)),
+            None => Box::new(show),
+        });
+        self
+    }
+}
+
+/// A portal and where it's mounted, if it is.
+struct Slot<T> {
+    target: PortalTarget,
+    renderer: SharedRender<T, T>,
+    show: Option<Show<T>>,
+    mounted: Option<MountedPortal>,
+}
+
+/// A container's portals, mounted and removed as their conditions change.
+pub(crate) struct Portals<T> {
+    data: Rc<State<T>>,
+    sender: MessageSender<T>,
+    /// The owning container's cache, invalidated along with any portal.
+    owner: Invalidator,
+    slots: RefCell<Vec<Slot<T>>>,
+}
+
+impl<T: 'static> Portals<T> {
+    pub(crate) fn new(
+        portals: Vec<Portal<T, T>>,
+        data: Rc<State<T>>,
+        sender: MessageSender<T>,
+        owner: Invalidator,
+    ) -> Self {
+        let slots = portals
+            .into_iter()
+            .map(|portal| Slot {
+                target: portal.target,
+                renderer: Rc::from(portal.renderer),
+                show: portal.show,
+                mounted: None,
+            })
+            .collect();
+        let portals = Portals {
+            data,
+            sender,
+            owner,
+            slots: RefCell::new(slots),
+        };
+        portals.sync();
+        portals
+    }
+
+    /// Mount portals that should show, remove the others and rerender the mounted ones.
+    pub(crate) fn sync(&self) {
+        for slot in self.slots.borrow_mut().iter_mut() {
+            let shown = match slot.show.as_ref() {
+                // keep things as they are while the model is being updated
+                Some(show) => self
+                    .data
+                    .render(|data| show(data))
+                    .unwrap_or(slot.mounted.is_some()),
+                None => true,
+            };
+            match (shown, slot.mounted.as_ref()) {
+                (true, Some(mounted)) => mounted.schedule_render(),
+                (true, None) => {
+                    slot.mounted = MountedPortal::mount(
+                        &slot.target,
+                        slot.renderer.clone(),
+                        self.data.clone(),
+                        self.sender.clone(),
+                        self.owner.child(),
+                    );
+                }
+                (false, _) => {
+                    slot.mounted.take();
+                }
+            }
+        }
+    }
+
+    /// Rerender the mounted portals, e.g. after something they read changed.
+    pub(crate) fn schedule_render(&self) {
+        for slot in self.slots.borrow().iter() {
+            if let Some(mounted) = slot.mounted.as_ref() {
+                mounted.schedule_render();
+            }
+        }
+    }
+
+    #[cfg(test)]
+    fn mounted(&self) -> usize {
+        let slots = self.slots.borrow();
+        slots.iter().filter(|slot| slot.mounted.is_some()).count()
+    }
+}
+
+/// A portal attached to the document, removed on drop.
+pub(crate) struct MountedPortal {
+    vdom: Option<Vdom>,
+    host: web_sys::Element,
+}
+
+impl MountedPortal {
+    /// Append a host element to the target and start rendering into it.
+    fn mount<T: 'static>(
+        target: &PortalTarget,
+        renderer: SharedRender<T, T>,
+        data: Rc<State<T>>,
+        sender: MessageSender<T>,
+        cache: Invalidator,
+    ) -> Option<MountedPortal> {
+        let document = web_sys::window()?.document()?;
+        let target = match target {
+            PortalTarget::Body => document.body()?.into(),
+            PortalTarget::Id(id) => document.get_element_by_id(id)?,
+            PortalTarget::Element(element) => element.clone(),
+        };
+        let host = document.create_element("div").ok()?;
+        host.set_attribute("data-afterglow-portal", "").ok()?;
+        target.append_child(&host).ok()?;
+
+        renderer.mounted(&sender);
+        let view = ContainerView::new(data, renderer, sender, cache);
+        let vdom = Vdom::new(&host, view);
+        Some(MountedPortal {
+            vdom: Some(vdom),
+            host,
+        })
+    }
+
+    fn schedule_render(&self) {
+        if let Some(vdom) = self.vdom.as_ref() {
+            vdom.weak().schedule_render();
+        }
+    }
+}
+
+impl Drop for MountedPortal {
+    fn drop(&mut self) {
+        // let dodrio clean up it's listeners before the host goes away
+        self.vdom.take();
+        self.host.remove();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    struct Modal {
+        open: bool,
+    }
+
+    impl LifeCycle for Modal {
+        fn new(_render_tx: Sender<((), oneshot::Sender<()>)>) -> Self {
+            Modal { open: true }
+        }
+    }
+
+    struct ModalView;
+
+    impl Renderer for ModalView {
+        type Target = Modal;
+        type Data = Modal;
+
+        fn view<'a>(
+            &self,
+            _target: &Self::Target,
+            ctx: &mut RenderContext<'a>,
+            _sender: &MessageSender<Self::Data>,
+        ) -> Node<'a> {
+            dodrio::builder::div(ctx.bump)
+                .attr("id", "portal-test-modal")
+                .finish()
+        }
+    }
+
+    struct PageView;
+
+    impl Renderer for PageView {
+        type Target = Modal;
+        type Data = Modal;
+
+        fn view<'a>(
+            &self,
+            _target: &Self::Target,
+            ctx: &mut RenderContext<'a>,
+            _sender: &MessageSender<Self::Data>,
+        ) -> Node<'a> {
+            dodrio::builder::div(ctx.bump).finish()
+        }
+
+        fn portals(&self) -> Vec<Portal<Modal, Modal>> {
+            vec![Portal::new(PortalTarget::Body, Box::new(ModalView))]
+        }
+    }
+
+    #[wasm_bindgen_test]
+    async fn portal_is_removed_with_container() {
+        let (render_tx, _) = mpsc::unbounded();
+        let container = Container::new(Modal { open: true }, Box::new(PageView), render_tx);
+        let document = web_sys::window().unwrap().document().unwrap();
+        let host = document
+            .query_selector("body > [data-afterglow-portal]")
+            .unwrap();
+        assert!(host.is_some());
+
+        drop(container);
+        assert!(document
+            .query_selector("body > [data-afterglow-portal]")
+            .unwrap()
+            .is_none());
+    }
+
+    struct GreetingView;
+
+    impl Renderer for GreetingView {
+        type Target = Modal;
+        type Data = Modal;
+
+        fn view<'a>(
+            &self,
+            _target: &Self::Target,
+            ctx: &mut RenderContext<'a>,
+            _sender: &MessageSender<Self::Data>,
+        ) -> Node<'a> {
+            let greeting = t!(ctx.bump, "portal.greeting");
+            dodrio::builder::div(ctx.bump)
+                .attr("id", "portal-test-greeting")
+                .children([dodrio::builder::text(greeting)])
+                .finish()
+        }
+    }
+
+    struct GreetingPageView;
+
+    impl Renderer for GreetingPageView {
+        type Target = Modal;
+        type Data = Modal;
+
+        fn view<'a>(
+            &self,
+            _target: &Self::Target,
+            ctx: &mut RenderContext<'a>,
+            _sender: &MessageSender<Self::Data>,
+        ) -> Node<'a> {
+            dodrio::builder::div(ctx.bump).finish()
+        }
+
+        fn portals(&self) -> Vec<Portal<Modal, Modal>> {
+            vec![Portal::new(PortalTarget::Body, Box::new(GreetingView))]
+        }
+    }
+
+    #[wasm_bindgen_test]
+    async fn portal_follows_locale() {
+        crate::i18n::add_catalog(Catalog::parse("en", "portal.greeting = Hello").unwrap());
+        crate::i18n::add_catalog(Catalog::parse("de", "portal.greeting = Hallo").unwrap());
+        crate::i18n::set_locale("en");
+
+        let document = web_sys::window().unwrap().document().unwrap();
+        let host = document.create_element("div").unwrap();
+        document.body().unwrap().append_child(&host).unwrap();
+        let (render_tx, _) = mpsc::unbounded();
+        let container = Container::new(Modal { open: true }, Box::new(GreetingPageView), render_tx);
+        let vdom = Vdom::new(&host, container);
+        let greeting = || {
+            document
+                .get_element_by_id("portal-test-greeting")
+                .and_then(|element| element.text_content())
+        };
+        assert_eq!(greeting().as_deref(), Some("Hello"));
+
+        crate::i18n::set_locale("de");
+        // the container rerenders and schedules the portal, which renders on the next frame
+        vdom.weak().render().await.unwrap();
+        vdom.weak().render().await.unwrap();
+        assert_eq!(greeting().as_deref(), Some("Hallo"));
+
+        drop(vdom);
+        host.remove();
+        crate::i18n::set_locale("en");
+    }
+
+    #[wasm_bindgen_test]
+    async fn portal_follows_condition() {
+        let data = Rc::new(State::new(Modal { open: false }));
+        let portal = Portal::new(PortalTarget::Body, Box::new(ModalView)).when(|modal| modal.open);
+        let portals = Portals::new(
+            vec![portal],
+            data.clone(),
+            mpsc::unbounded().0,
+            Invalidator::default(),
+        );
+        assert_eq!(portals.mounted(), 0);
+
+        data.update(|modal| modal.open = true).unwrap();
+        portals.sync();
+        assert_eq!(portals.mounted(), 1);
+
+        data.update(|modal| modal.open = false).unwrap();
+        portals.sync();
+        assert_eq!(portals.mounted(), 0);
+    }
+}
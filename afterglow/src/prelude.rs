@@ -1,7 +1,27 @@
+pub use crate::backend::{render_to_string, Backend};
 pub use crate::bus::*;
 pub use crate::container::*;
+pub use crate::events::{self, ListenerOptions};
+pub use crate::forms::{Field, Form, FormHost, FormMsg, ValidateOn};
+pub use crate::head::Head;
+pub use crate::html::html;
+pub use crate::i18n::{AddCatalog, Catalog, SetLocale};
+pub use crate::lazy::{Lazy, OnLeave};
+pub use crate::markup::{Markup, Sanitizer};
 pub use crate::messenger::*;
+pub use crate::node_ref::NodeRef;
+pub use crate::portal::{Portal, PortalTarget};
 pub use crate::renderer::*;
+pub use crate::responsive::{Breakpoint, Responsive};
+pub use crate::shortcuts::{Shortcut, ShortcutHandle};
+pub use crate::spawner::{spawn, spawn_local, JoinHandle};
+pub use crate::state::*;
+pub use crate::style::Style;
+pub use crate::t;
+pub use crate::theme::{theme, SetTheme, Theme};
+pub use crate::transition::{Stage, Transition, TransitionMsg, Transitions};
+pub use crate::virtual_list::{RowHeights, VirtualList, VirtualMsg};
+pub use crate::vnode::{self, VNode, View};
 
 pub use dodrio::{self, builder::text, bumpalo::format as bf, Node, RenderContext};
 pub use futures::channel::{
@@ -1,7 +1,15 @@
+use crate::portal::Portals;
 use crate::prelude::*;
+use crate::vnode::{Component, VNode};
 use async_trait::*;
+use dodrio::bumpalo::collections::Vec as BumpVec;
+use dodrio::Cached;
+use std::any::TypeId;
+use std::cell::{Cell, RefCell};
+use std::rc::{Rc, Weak};
 
 pub(crate) type Render<T, D> = Box<dyn Renderer<Target = T, Data = D>>;
+pub(crate) type SharedRender<T, D> = Rc<dyn Renderer<Target = T, Data = D>>;
 
 pub trait Renderer {
     type Target;
@@ -13,20 +21,321 @@ pub trait Renderer {
         ctx: &mut RenderContext<'a>,
         sender: &MessageSender<Self::Data>,
     ) -> Node<'a>;
+
+    /// Sibling nodes spliced into the parent's children instead of the single root of `view`,
+    /// e.g. table rows or grid items. `None` renders `view` as usual.
+    ///
+    /// Parents use `Container::render_children` to splice them, `view` is still used where a single
+    /// root is needed. Fragments are rebuilt whenever the parent renders. This runs before the
+    /// cached `view` on every render of the parent, so renderers without a fragment must return
+    /// `None` without rendering.
+    fn view_children<'a>(
+        &self,
+        _target: &Self::Target,
+        _ctx: &mut RenderContext<'a>,
+        _sender: &MessageSender<Self::Data>,
+    ) -> Option<BumpVec<'a, Node<'a>>> {
+        None
+    }
+
+    /// Backend independent view, `None` for renderers that build dodrio nodes directly.
+    fn vnode(&self, _target: &Self::Target, _sender: &MessageSender<Self::Data>) -> Option<VNode> {
+        None
+    }
+
+    /// Css injected while a container rendered by this renderer is alive.
+    fn style(&self) -> Option<&'static Style> {
+        None
+    }
+
+    /// Every css the renderer needs along with the renderer type it belongs to, styles are
+    /// injected once per type. Defaults to `style`, renderers rendering through others collect
+    /// theirs.
+    fn styles(&self) -> Vec<(TypeId, &'static Style)>
+    where
+        Self: 'static,
+    {
+        let renderer = TypeId::of::<Self>();
+        self.style()
+            .into_iter()
+            .map(|style| (renderer, style))
+            .collect()
+    }
+
+    /// Called once a container starts rendering with it, before the first render, e.g. to listen
+    /// to the browser.
+    fn mounted(&self, _sender: &MessageSender<Self::Data>) {}
+
+    /// Subtrees rendered into other parts of the document, see `Portal`.
+    fn portals(&self) -> Vec<Portal<Self::Target, Self::Data>> {
+        vec![]
+    }
 }
 
 impl<'a, T> dodrio::Render<'a> for Container<T>
 where
-    T: LifeCycle,
+    T: LifeCycle + 'static,
 {
     fn render(&self, cx: &mut RenderContext<'a>) -> Node<'a> {
-        let bump = cx.bump;
-        if let Some(data) = self.data.try_lock() {
-            self.renderer.view(&*data, cx, &self.sender)
-        } else {
-            dodrio::builder::template(bump).finish()
+        Container::render(self, cx)
+    }
+}
+
+thread_local! {
+    static RENDERING: RefCell<Vec<Rc<CacheState>>> = const { RefCell::new(Vec::new()) };
+}
+
+#[derive(Default)]
+struct CacheState {
+    dirty: Cell<bool>,
+    parent: RefCell<Weak<CacheState>>,
+}
+
+impl CacheState {
+    fn invalidate(&self) {
+        self.dirty.set(true);
+        if let Some(parent) = self.parent.borrow().upgrade() {
+            parent.invalidate();
+        }
+    }
+}
+
+/// Handle for marking a container's cached view as stale.
+///
+/// Invalidating a container also invalidates every container that rendered it,
+/// so the next frame walks down to it instead of reusing an outdated subtree.
+#[derive(Clone, Default)]
+pub struct Invalidator(Rc<CacheState>);
+
+impl Invalidator {
+    /// Force the container to rerun it's renderer on the next frame.
+    pub fn invalidate(&self) {
+        self.0.invalidate();
+    }
+
+    /// Remember the container currently rendering as the parent of this one.
+    pub(crate) fn attach(&self) {
+        let moved = RENDERING.with(|stack| match stack.borrow().last() {
+            Some(parent) if !Rc::ptr_eq(parent, &self.0) => {
+                let parent = Rc::downgrade(parent);
+                !self.0.parent.replace(parent.clone()).ptr_eq(&parent)
+            }
+            _ => false,
+        });
+        if moved {
+            // the nesting decides whose head wins
+            crate::head::refresh();
+        }
+    }
+
+    /// A new invalidator that also invalidates this one, for views rendered outside of the
+    /// container's subtree like portals.
+    pub(crate) fn child(&self) -> Invalidator {
+        let child = Invalidator::default();
+        child.0.parent.replace(Rc::downgrade(&self.0));
+        child
+    }
+
+    /// How many containers rendered this one, as of it's last render.
+    pub(crate) fn depth(&self) -> usize {
+        let mut depth = 0;
+        let mut parent = self.0.parent.borrow().upgrade();
+        while let Some(state) = parent {
+            depth += 1;
+            parent = state.parent.borrow().upgrade();
+        }
+        depth
+    }
+
+    /// Consume the dirty flag, returns whether the cached view should be dropped.
+    pub(crate) fn take_dirty(&self) -> bool {
+        self.0.dirty.replace(false)
+    }
+}
+
+/// Containers whose rendering read some shared value, e.g. the current theme.
+///
+/// Values call `track` when they are read and `invalidate` when they change, dropped containers
+/// are forgotten on the way.
+#[derive(Default)]
+pub(crate) struct Dependents(Vec<Weak<CacheState>>);
+
+impl Dependents {
+    /// Remember the container currently rendering, if any.
+    pub(crate) fn track(&mut self) {
+        RENDERING.with(|stack| {
+            if let Some(current) = stack.borrow().last() {
+                let current = Rc::downgrade(current);
+                if !self.0.iter().any(|known| known.ptr_eq(&current)) {
+                    self.0.push(current);
+                }
+            }
+        });
+    }
+
+    pub(crate) fn invalidate(&mut self) {
+        self.0.retain(|dependent| match dependent.upgrade() {
+            Some(state) => {
+                state.invalidate();
+                true
+            }
+            None => false,
+        });
+    }
+
+    #[cfg(test)]
+    pub(crate) fn len(&self) -> usize {
+        self.0.len()
+    }
+}
+
+/// Get the invalidator of the container whose renderer is currently running.
+pub fn current_invalidator() -> Option<Invalidator> {
+    RENDERING.with(|stack| stack.borrow().last().cloned().map(Invalidator))
+}
+
+/// The part of a container that dodrio caches between frames.
+pub(crate) struct ContainerView<T> {
+    parts: Option<ViewParts<T>>,
+}
+
+struct ViewParts<T> {
+    data: Rc<State<T>>,
+    renderer: SharedRender<T, T>,
+    sender: MessageSender<T>,
+    cache: Invalidator,
+}
+
+impl<T> Default for ContainerView<T> {
+    fn default() -> Self {
+        ContainerView { parts: None }
+    }
+}
+
+impl<T> ContainerView<T> {
+    pub(crate) fn new(
+        data: Rc<State<T>>,
+        renderer: SharedRender<T, T>,
+        sender: MessageSender<T>,
+        cache: Invalidator,
+    ) -> Self {
+        let parts = Some(ViewParts {
+            data,
+            renderer,
+            sender,
+            cache,
+        });
+        ContainerView { parts }
+    }
+}
+
+impl<T: 'static> ContainerView<T> {
+    /// Run `Renderer::view_children`, `None` if the renderer has no fragment.
+    fn children<'a>(&self, cx: &mut RenderContext<'a>) -> Option<BumpVec<'a, Node<'a>>> {
+        let parts = self.parts.as_ref()?;
+        RENDERING.with(|stack| stack.borrow_mut().push(parts.cache.0.clone()));
+        let nodes = parts
+            .data
+            .render(|data| parts.renderer.view_children(data, cx, &parts.sender))
+            .ok()
+            .flatten();
+        RENDERING.with(|stack| stack.borrow_mut().pop());
+        nodes
+    }
+}
+
+/// A container's view together with dodrio's cache of it's last rendering.
+pub(crate) struct CachedView<T> {
+    view: Cached<ContainerView<T>>,
+    cache: Invalidator,
+    portals: Weak<Portals<T>>,
+}
+
+impl<T: 'static> CachedView<T> {
+    pub(crate) fn new(
+        view: ContainerView<T>,
+        cache: Invalidator,
+        portals: Weak<Portals<T>>,
+    ) -> Self {
+        CachedView {
+            view: Cached::new(view),
+            cache,
+            portals,
+        }
+    }
+
+    /// Drop the cached view if it's stale, mounted portals render the same model and follow.
+    fn refresh(&self) {
+        self.cache.attach();
+        if self.cache.take_dirty() {
+            Cached::invalidate(&self.view);
+            if let Some(portals) = self.portals.upgrade() {
+                portals.schedule_render();
+            }
         }
     }
+
+    pub(crate) fn render<'a>(&self, cx: &mut RenderContext<'a>) -> Node<'a> {
+        self.refresh();
+        dodrio::Render::render(&self.view, cx)
+    }
+
+    /// The renderer's fragment, or the cached view as the only node.
+    pub(crate) fn render_children<'a>(&self, cx: &mut RenderContext<'a>) -> BumpVec<'a, Node<'a>> {
+        self.refresh();
+        if let Some(nodes) = self.view.children(cx) {
+            return nodes;
+        }
+        let mut nodes = BumpVec::new_in(cx.bump);
+        nodes.push(dodrio::Render::render(&self.view, cx));
+        nodes
+    }
+}
+
+impl<T: 'static> Component for CachedView<T> {
+    fn dodrio<'a>(&self, cx: &mut RenderContext<'a>) -> Node<'a> {
+        self.render(cx)
+    }
+
+    fn dodrio_children<'a>(&self, cx: &mut RenderContext<'a>) -> BumpVec<'a, Node<'a>> {
+        self.render_children(cx)
+    }
+
+    fn vnode(&self) -> Option<VNode> {
+        self.cache.attach();
+        let parts = self.view.parts.as_ref()?;
+        RENDERING.with(|stack| stack.borrow_mut().push(parts.cache.0.clone()));
+        let node = parts
+            .data
+            .render(|data| parts.renderer.vnode(data, &parts.sender))
+            .ok()
+            .flatten()
+            .map(VNode::expand);
+        RENDERING.with(|stack| stack.borrow_mut().pop());
+        node
+    }
+}
+
+impl<'a, T: 'static> dodrio::Render<'a> for ContainerView<T> {
+    fn render(&self, cx: &mut RenderContext<'a>) -> Node<'a> {
+        let bump = cx.bump;
+        let parts = match self.parts.as_ref() {
+            Some(parts) => parts,
+            None => return dodrio::builder::template(bump).finish(),
+        };
+
+        RENDERING.with(|stack| stack.borrow_mut().push(parts.cache.0.clone()));
+        let node = parts
+            .data
+            .render(|data| parts.renderer.view(data, cx, &parts.sender))
+            .unwrap_or_else(|_| {
+                // don't keep the placeholder around once the data is available again.
+                parts.cache.0.dirty.set(true);
+                dodrio::builder::template(bump).finish()
+            });
+        RENDERING.with(|stack| stack.borrow_mut().pop());
+        node
+    }
 }
 
 #[async_trait]
@@ -43,9 +352,60 @@ pub trait AsyncRenderer {
 }
 
 #[cfg(test)]
+#[allow(dead_code)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn invalidate_reaches_ancestors() {
+        let root = Invalidator::default();
+        let child = Invalidator::default();
+        let grandchild = Invalidator::default();
+        child.0.parent.replace(Rc::downgrade(&root.0));
+        grandchild.0.parent.replace(Rc::downgrade(&child.0));
+
+        grandchild.invalidate();
+        assert!(root.take_dirty());
+        assert!(child.take_dirty());
+        assert!(grandchild.take_dirty());
+        assert!(!grandchild.take_dirty());
+    }
+
+    #[test]
+    fn attach_uses_rendering_container() {
+        let parent = Invalidator::default();
+        let child = Invalidator::default();
+        RENDERING.with(|stack| stack.borrow_mut().push(parent.0.clone()));
+        child.attach();
+        assert!(current_invalidator().is_some());
+        RENDERING.with(|stack| stack.borrow_mut().pop());
+
+        child.invalidate();
+        assert!(parent.take_dirty());
+        assert!(current_invalidator().is_none());
+    }
+
+    #[test]
+    fn dependents_forget_dropped_containers() {
+        let mut dependents = Dependents::default();
+        let kept = Invalidator::default();
+        for state in [kept.0.clone(), Rc::new(CacheState::default())] {
+            RENDERING.with(|stack| stack.borrow_mut().push(state));
+            dependents.track();
+            dependents.track();
+            RENDERING.with(|stack| stack.borrow_mut().pop());
+        }
+        assert_eq!(dependents.len(), 2);
+
+        dependents.invalidate();
+        assert!(kept.take_dirty());
+        assert_eq!(dependents.len(), 1);
+        drop(kept);
+        dependents.invalidate();
+        assert_eq!(dependents.len(), 0);
+    }
+
+    #[allow(non_camel_case_types)]
     pub enum Device {
         pc,
         mobile,
@@ -63,7 +423,7 @@ mod tests {
             &self,
             target: &Self::Target,
             ctx: &mut RenderContext<'a>,
-            sender: &MessageSender<Self::Data>,
+            _sender: &MessageSender<Self::Data>,
         ) -> Node<'a> {
             let bump = ctx.bump;
             let state = bf!(in bump, "{}", &target.state).into_bump_str();
@@ -0,0 +1,440 @@
+//! Pick one of several renderers for a model depending on the viewport or the container's width.
+//!
+//! ```ignore
+//! let renderer = Responsive::new(Device::pc)
+//!     .media("(max-width: 600px)", Device::mobile)
+//!     .max_width(400.0, Device::compact);
+//! Container::new(data, Box::new(renderer), render_tx);
+//! ```
+//!
+//! Media queries follow `matchMedia`, width breakpoints measure the element wrapping the view with
+//! a `ResizeObserver`. The first matching breakpoint wins, the fallback renders when none does.
+//! The container rerenders whenever another renderer should be picked.
+//!
+//! Listening starts once the container is created, the wrapper is observed after each render since
+//! it only exists once the document is patched. The styles of every renderer are injected, portals
+//! are mounted while their renderer is the active one.
+
+use crate::prelude::*;
+use crate::renderer::Render;
+use crate::vnode::el;
+use dodrio::bumpalo::collections::Vec as BumpVec;
+use std::any::TypeId;
+use std::cell::{Cell, RefCell};
+use std::marker::PhantomData;
+use std::rc::Rc;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Breakpoint {
+    /// A media query such as `(max-width: 600px)` or `(prefers-color-scheme: dark)`.
+    Media(&'static str),
+    /// The container is at least this many pixels wide.
+    MinWidth(f64),
+    /// The container is at most this many pixels wide.
+    MaxWidth(f64),
+}
+
+/// What breakpoints are matched against, shared with the browser callbacks.
+#[derive(Default)]
+struct Watched {
+    media: RefCell<Vec<Option<web_sys::MediaQueryList>>>,
+    width: Cell<Option<f64>>,
+    active: Cell<Option<usize>>,
+    #[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+    observed: RefCell<Option<web_sys::Element>>,
+}
+
+type ResizeWatch = (web_sys::ResizeObserver, Closure<dyn FnMut(js_sys::Array)>);
+
+/// Browser callbacks, kept apart from `Watched` so they don't keep themselves alive.
+#[derive(Default)]
+struct Watchers {
+    _media: Vec<EventListener>,
+    resize: Option<ResizeWatch>,
+}
+
+impl Drop for Watchers {
+    fn drop(&mut self) {
+        if let Some((observer, _)) = self.resize.take() {
+            observer.disconnect();
+        }
+    }
+}
+
+/// A renderer switching between other renderers of the same model.
+pub struct Responsive<T, D> {
+    fallback: Render<T, D>,
+    cases: Vec<(Breakpoint, Render<T, D>)>,
+    watched: Rc<Watched>,
+    watchers: RefCell<Watchers>,
+    node_ref: NodeRef,
+}
+
+impl<T, D> Responsive<T, D> {
+    pub fn new(fallback: impl Renderer<Target = T, Data = D> + 'static) -> Self {
+        Responsive {
+            fallback: Box::new(fallback),
+            cases: vec![],
+            watched: Rc::default(),
+            watchers: RefCell::default(),
+            node_ref: NodeRef::new(),
+        }
+    }
+
+    pub fn when(
+        mut self,
+        breakpoint: Breakpoint,
+        renderer: impl Renderer<Target = T, Data = D> + 'static,
+    ) -> Self {
+        self.cases.push((breakpoint, Box::new(renderer)));
+        self
+    }
+
+    pub fn media(
+        self,
+        query: &'static str,
+        renderer: impl Renderer<Target = T, Data = D> + 'static,
+    ) -> Self {
+        self.when(Breakpoint::Media(query), renderer)
+    }
+
+    pub fn min_width(
+        self,
+        width: f64,
+        renderer: impl Renderer<Target = T, Data = D> + 'static,
+    ) -> Self {
+        self.when(Breakpoint::MinWidth(width), renderer)
+    }
+
+    pub fn max_width(
+        self,
+        width: f64,
+        renderer: impl Renderer<Target = T, Data = D> + 'static,
+    ) -> Self {
+        self.when(Breakpoint::MaxWidth(width), renderer)
+    }
+
+    /// Index of the breakpoint in use, `None` for the fallback.
+    pub fn active(&self) -> Option<usize> {
+        select(self.cases.iter().map(|(bp, _)| bp), &self.watched)
+    }
+
+    fn renderer(&self) -> &Render<T, D> {
+        self.watched.active.set(self.active());
+        match self.active() {
+            Some(index) => &self.cases[index].1,
+            None => &self.fallback,
+        }
+    }
+
+    fn measures_width(&self) -> bool {
+        self.cases
+            .iter()
+            .any(|(breakpoint, _)| !matches!(breakpoint, Breakpoint::Media(_)))
+    }
+
+    /// Every renderer, with the index of it's breakpoint.
+    fn renderers(&self) -> impl Iterator<Item = (Option<usize>, &Render<T, D>)> {
+        let cases = self.cases.iter().enumerate();
+        std::iter::once((None, &self.fallback))
+            .chain(cases.map(|(index, (_, renderer))| (Some(index), renderer)))
+    }
+}
+
+fn select<'b>(
+    breakpoints: impl Iterator<Item = &'b Breakpoint>,
+    watched: &Watched,
+) -> Option<usize> {
+    let media = watched.media.borrow();
+    let width = watched.width.get();
+    breakpoints
+        .enumerate()
+        .position(|(index, breakpoint)| match breakpoint {
+            Breakpoint::Media(_) => media
+                .get(index)
+                .and_then(Option::as_ref)
+                .is_some_and(web_sys::MediaQueryList::matches),
+            Breakpoint::MinWidth(min) => width.is_some_and(|width| width >= *min),
+            Breakpoint::MaxWidth(max) => width.is_some_and(|width| width <= *max),
+        })
+}
+
+impl<T: 'static, D: 'static> Responsive<T, D> {
+    /// Start listening for changes.
+    fn watch(&self, sender: &MessageSender<D>) {
+        let mut watchers = self.watchers.borrow_mut();
+        let breakpoints: Vec<_> = self.cases.iter().map(|(bp, _)| bp.clone()).collect();
+        let refresh = {
+            let watched = Rc::downgrade(&self.watched);
+            let sender = sender.clone();
+            move || {
+                if let Some(watched) = watched.upgrade() {
+                    if select(breakpoints.iter(), &watched) != watched.active.get() {
+                        Refresh(PhantomData).send(&sender);
+                    }
+                }
+            }
+        };
+        self.watch_media(&mut watchers, Rc::new(refresh.clone()));
+        if self.measures_width() {
+            self.watch_width(&mut watchers, refresh);
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn watch_media(&self, watchers: &mut Watchers, refresh: Rc<dyn Fn()>) {
+        let window = match web_sys::window() {
+            Some(window) => window,
+            None => return,
+        };
+        let mut media = self.watched.media.borrow_mut();
+        for (breakpoint, _) in self.cases.iter() {
+            let list = match breakpoint {
+                Breakpoint::Media(query) => window.match_media(query).ok().flatten(),
+                _ => None,
+            };
+            if let Some(list) = list.as_ref() {
+                let refresh = refresh.clone();
+                watchers
+                    ._media
+                    .push(EventListener::new(list, "change", move |_| refresh()));
+            }
+            media.push(list);
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn watch_media(&self, _watchers: &mut Watchers, _refresh: Rc<dyn Fn()>) {}
+
+    #[cfg(target_arch = "wasm32")]
+    fn watch_width(&self, watchers: &mut Watchers, refresh: impl Fn() + 'static) {
+        let watched = Rc::downgrade(&self.watched);
+        let callback = Closure::wrap(Box::new(move |entries: js_sys::Array| {
+            let entry = entries
+                .get(0)
+                .dyn_into::<web_sys::ResizeObserverEntry>()
+                .ok();
+            if let (Some(entry), Some(watched)) = (entry, watched.upgrade()) {
+                watched.width.set(Some(entry.content_rect().width()));
+                refresh();
+            }
+        }) as Box<dyn FnMut(js_sys::Array)>);
+        watchers.resize = web_sys::ResizeObserver::new(callback.as_ref().unchecked_ref())
+            .ok()
+            .map(|observer| (observer, callback));
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn watch_width(&self, _watchers: &mut Watchers, _refresh: impl Fn() + 'static) {}
+
+    /// Measure the wrapper once dodrio patched it into the document.
+    #[cfg(target_arch = "wasm32")]
+    fn observe(&self) {
+        let observer = match self.watchers.borrow().resize.as_ref() {
+            Some((observer, _)) => observer.clone(),
+            None => return,
+        };
+        let node_ref = self.node_ref.clone();
+        let watched = Rc::downgrade(&self.watched);
+        spawn_local(async move {
+            let (element, watched) = match (node_ref.get(), watched.upgrade()) {
+                (Some(element), Some(watched)) => (element, watched),
+                _ => return,
+            };
+            let mut observed = watched.observed.borrow_mut();
+            if observed.as_ref() != Some(&element) {
+                if let Some(previous) = observed.replace(element.clone()) {
+                    observer.unobserve(&previous);
+                }
+                observer.observe(&element);
+            }
+        });
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn observe(&self) {}
+}
+
+impl<T: 'static, D: 'static> Renderer for Responsive<T, D> {
+    type Target = T;
+    type Data = D;
+
+    fn view<'a>(
+        &self,
+        target: &Self::Target,
+        ctx: &mut RenderContext<'a>,
+        sender: &MessageSender<Self::Data>,
+    ) -> Node<'a> {
+        let node = self.renderer().view(target, ctx, sender);
+        if !self.measures_width() {
+            return node;
+        }
+        self.observe();
+        let bump = ctx.bump;
+        dodrio::builder::div(bump)
+            .attr(
+                NodeRef::ATTR,
+                dodrio::bumpalo::collections::String::from_str_in(&self.node_ref.value(), bump)
+                    .into_bump_str(),
+            )
+            .child(node)
+            .finish()
+    }
+
+    fn view_children<'a>(
+        &self,
+        target: &Self::Target,
+        ctx: &mut RenderContext<'a>,
+        sender: &MessageSender<Self::Data>,
+    ) -> Option<BumpVec<'a, Node<'a>>> {
+        // the measuring wrapper needs a single root
+        if self.measures_width() {
+            return None;
+        }
+        self.renderer().view_children(target, ctx, sender)
+    }
+
+    fn vnode(&self, target: &Self::Target, sender: &MessageSender<Self::Data>) -> Option<VNode> {
+        let node = self.renderer().vnode(target, sender)?;
+        if !self.measures_width() {
+            return Some(node);
+        }
+        self.observe();
+        Some(el("div").node_ref(&self.node_ref).child(node).finish())
+    }
+
+    fn mounted(&self, sender: &MessageSender<Self::Data>) {
+        self.watch(sender);
+        for (_, renderer) in self.renderers() {
+            renderer.mounted(sender);
+        }
+    }
+
+    fn styles(&self) -> Vec<(TypeId, &'static Style)> {
+        self.renderers()
+            .flat_map(|(_, renderer)| renderer.styles())
+            .collect()
+    }
+
+    /// Portals of every renderer, mounted while it's the active one.
+    fn portals(&self) -> Vec<Portal<Self::Target, Self::Data>> {
+        let breakpoints: Rc<[Breakpoint]> = self.cases.iter().map(|(bp, _)| bp.clone()).collect();
+        let mut portals = vec![];
+        for (index, renderer) in self.renderers() {
+            for portal in renderer.portals() {
+                let watched = Rc::downgrade(&self.watched);
+                let breakpoints = breakpoints.clone();
+                portals.push(portal.when(move |_| {
+                    watched
+                        .upgrade()
+                        .is_some_and(|watched| select(breakpoints.iter(), &watched) == index)
+                }));
+            }
+        }
+        portals
+    }
+}
+
+/// Rerenders the container after another renderer became active.
+struct Refresh<D>(PhantomData<D>);
+
+impl<D> Messenger for Refresh<D> {
+    type Target = D;
+
+    fn update(
+        self: Box<Self>,
+        _target: &mut Self::Target,
+        _sender: &MessageSender<Self::Target>,
+        _render_tx: &Sender<((), oneshot::Sender<()>)>,
+    ) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PC: Style = Style::new("pc", "& { display: grid }");
+    const MOBILE: Style = Style::new("mobile", "& { display: block }");
+
+    struct Device(&'static str);
+
+    impl Renderer for Device {
+        type Target = ();
+        type Data = ();
+
+        fn view<'a>(
+            &self,
+            _target: &Self::Target,
+            _ctx: &mut RenderContext<'a>,
+            _sender: &MessageSender<Self::Data>,
+        ) -> Node<'a> {
+            text(self.0)
+        }
+
+        fn vnode(
+            &self,
+            _target: &Self::Target,
+            _sender: &MessageSender<Self::Data>,
+        ) -> Option<VNode> {
+            Some(crate::vnode::text(self.0))
+        }
+
+        fn style(&self) -> Option<&'static Style> {
+            match self.0 {
+                "pc" => Some(&PC),
+                "mobile" => Some(&MOBILE),
+                _ => None,
+            }
+        }
+    }
+
+    #[test]
+    fn picks_first_matching_breakpoint() {
+        let responsive = Responsive::new(Device("pc"))
+            .media("(orientation: portrait)", Device("mobile"))
+            .max_width(600.0, Device("mobile"))
+            .min_width(1200.0, Device("wide"));
+        assert_eq!(responsive.active(), None);
+
+        responsive.watched.width.set(Some(480.0));
+        assert_eq!(responsive.active(), Some(1));
+        responsive.watched.width.set(Some(900.0));
+        assert_eq!(responsive.active(), None);
+        responsive.watched.width.set(Some(1400.0));
+        assert_eq!(responsive.active(), Some(2));
+    }
+
+    #[test]
+    fn styles_of_every_renderer() {
+        let responsive = Responsive::new(Device("pc"))
+            .media("(orientation: portrait)", Device("mobile"))
+            .min_width(1200.0, Device("wide"));
+        let styles = responsive.styles();
+        assert_eq!(styles.len(), 2);
+        assert!(styles
+            .iter()
+            .all(|(renderer, _)| *renderer == TypeId::of::<Device>()));
+        assert_eq!(styles[0].1.scope(), PC.scope());
+        assert_eq!(styles[1].1.scope(), MOBILE.scope());
+    }
+
+    #[test]
+    fn vnode_wraps_measured_view() {
+        let (sender, _) = mpsc::unbounded();
+        let media =
+            Responsive::new(Device("pc")).media("(orientation: portrait)", Device("mobile"));
+        assert_eq!(
+            render_to_string(&media, &(), &sender).as_deref(),
+            Some("pc")
+        );
+
+        let width = Responsive::new(Device("pc")).max_width(600.0, Device("mobile"));
+        width.mounted(&sender);
+        width.watched.width.set(Some(480.0));
+        let html = render_to_string(&width, &(), &sender).unwrap();
+        assert!(html.starts_with("<div data-ag-ref="), "{}", html);
+        assert!(html.ends_with(">mobile</div>"), "{}", html);
+    }
+}
@@ -0,0 +1,492 @@
+//! Keyboard shortcuts shared by every container.
+//!
+//! Shortcuts are chords such as `Ctrl+K` or sequences of chords such as `g i`, mapped to a
+//! `Messenger`. A single `keydown` listener on the window serves all of them, and a shortcut
+//! stays registered until its handle is dropped. Containers return handles from
+//! `LifeCycle::shortcuts` to keep them for their whole lifetime:
+//!
+//! ```ignore
+//! fn shortcuts(sender: &MessageSender<Self>) -> Vec<ShortcutHandle> {
+//!     vec![
+//!         Shortcut::bind("Ctrl+K").describe("Search").send(sender, || Msg::OpenSearch),
+//!         Shortcut::bind("g i").describe("Go to inbox").send(sender, || Msg::Inbox),
+//!     ]
+//!     .into_iter()
+//!     .filter_map(|shortcut| shortcut.map_err(|err| log::error!("{}", err)).ok())
+//!     .collect()
+//! }
+//! ```
+//!
+//! Shortcuts without modifiers are ignored while the user types into a form control.
+
+use crate::prelude::*;
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+use web_sys::KeyboardEvent;
+
+/// Keys pressed together, e.g. `Ctrl+Shift+P`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Chord {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    pub meta: bool,
+    /// The key in lower case, as in `KeyboardEvent.key`.
+    pub key: String,
+}
+
+impl Chord {
+    fn new(ctrl: bool, alt: bool, shift: bool, meta: bool, key: &str) -> Self {
+        let key = key.to_lowercase();
+        // symbols already tell whether shift was held, `?` is `Shift+/` on most layouts
+        let symbol = key.chars().count() == 1 && !key.chars().all(char::is_alphanumeric);
+        Chord {
+            ctrl,
+            alt,
+            shift: shift && !symbol,
+            meta,
+            key,
+        }
+    }
+
+    pub fn from_event(event: &KeyboardEvent) -> Option<Self> {
+        let key = event.key();
+        if matches!(key.as_str(), "Control" | "Alt" | "Shift" | "Meta") {
+            return None;
+        }
+        Some(Chord::new(
+            event.ctrl_key(),
+            event.alt_key(),
+            event.shift_key(),
+            event.meta_key(),
+            &key,
+        ))
+    }
+
+    fn has_modifier(&self) -> bool {
+        self.ctrl || self.alt || self.meta
+    }
+}
+
+impl std::str::FromStr for Chord {
+    type Err = ShortcutError;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        let invalid = || ShortcutError::Invalid(source.to_string());
+        let (mut ctrl, mut alt, mut shift, mut meta) = (false, false, false, false);
+        // a trailing `+` is the plus key itself, e.g. `Ctrl++`
+        let (modifiers, key) = match source.strip_suffix("++") {
+            Some(modifiers) => (modifiers, "+"),
+            None => match source.rsplit_once('+') {
+                Some((modifiers, key)) => (modifiers, key),
+                None => ("", source),
+            },
+        };
+        for modifier in modifiers.split('+').filter(|m| !m.is_empty()) {
+            match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" => ctrl = true,
+                "alt" | "option" => alt = true,
+                "shift" => shift = true,
+                "meta" | "cmd" | "super" => meta = true,
+                _ => return Err(invalid()),
+            }
+        }
+        if key.is_empty() {
+            return Err(invalid());
+        }
+        let key = match key.to_lowercase().as_str() {
+            "esc" => "escape".to_string(),
+            "space" => " ".to_string(),
+            "up" | "down" | "left" | "right" => format!("arrow{}", key.to_lowercase()),
+            _ => key.to_string(),
+        };
+        Ok(Chord::new(ctrl, alt, shift, meta, &key))
+    }
+}
+
+impl fmt::Display for Chord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (held, name) in [
+            (self.ctrl, "Ctrl+"),
+            (self.alt, "Alt+"),
+            (self.shift, "Shift+"),
+            (self.meta, "Meta+"),
+        ] {
+            if held {
+                f.write_str(name)?;
+            }
+        }
+        match self.key.as_str() {
+            " " => f.write_str("Space"),
+            key if key.chars().count() == 1 => f.write_str(&key.to_uppercase()),
+            key => {
+                let mut chars = key.chars();
+                let first = chars.next().map(|c| c.to_uppercase().to_string());
+                write!(f, "{}{}", first.unwrap_or_default(), chars.as_str())
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ShortcutError {
+    Invalid(String),
+    /// Clashes with a shortcut registered earlier, given by its description.
+    Conflict {
+        shortcut: String,
+        existing: String,
+    },
+}
+
+impl fmt::Display for ShortcutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShortcutError::Invalid(source) => write!(f, "invalid shortcut `{}`", source),
+            ShortcutError::Conflict { shortcut, existing } => {
+                write!(f, "`{}` conflicts with \"{}\"", shortcut, existing)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ShortcutError {}
+
+/// Sequences of keys pressed within this many milliseconds of each other belong together.
+const SEQUENCE_TIMEOUT: f64 = 1000.0;
+
+type Action = Rc<dyn Fn()>;
+
+struct Binding {
+    id: u32,
+    sequence: Vec<Chord>,
+    description: String,
+    scope: Option<NodeRef>,
+    action: Action,
+}
+
+#[derive(Default)]
+struct Registry {
+    next_id: u32,
+    bindings: Vec<Binding>,
+    pressed: Vec<Chord>,
+    last_press: f64,
+    listener: Option<EventListener>,
+}
+
+impl Registry {
+    fn conflict(&self, sequence: &[Chord], scope: &Option<NodeRef>) -> Option<&Binding> {
+        self.bindings.iter().find(|binding| {
+            let len = binding.sequence.len().min(sequence.len());
+            binding.scope == *scope && binding.sequence[..len] == sequence[..len]
+        })
+    }
+
+    /// Handle a chord, returns the action to run and whether the key was used.
+    fn press(
+        &mut self,
+        chord: Chord,
+        now: f64,
+        active: impl Fn(&Option<NodeRef>) -> bool,
+    ) -> (Option<Action>, bool) {
+        if now - self.last_press > SEQUENCE_TIMEOUT {
+            self.pressed.clear();
+        }
+        self.last_press = now;
+        self.pressed.push(chord.clone());
+
+        for attempt in 0..2 {
+            let candidates: Vec<_> = self
+                .bindings
+                .iter()
+                .filter(|binding| binding.sequence.starts_with(&self.pressed))
+                .filter(|binding| active(&binding.scope))
+                .collect();
+            // scoped shortcuts win over global ones
+            let complete = candidates
+                .iter()
+                .filter(|binding| binding.sequence.len() == self.pressed.len())
+                .max_by_key(|binding| binding.scope.is_some());
+            if let Some(binding) = complete {
+                let action = binding.action.clone();
+                self.pressed.clear();
+                return (Some(action), true);
+            }
+            if !candidates.is_empty() {
+                return (None, true);
+            }
+            // not part of a known sequence, maybe it starts a new one
+            if attempt == 0 && self.pressed.len() > 1 {
+                self.pressed = vec![chord.clone()];
+            } else {
+                break;
+            }
+        }
+        self.pressed.clear();
+        (None, false)
+    }
+}
+
+thread_local! {
+    static REGISTRY: RefCell<Registry> = RefCell::new(Registry::default());
+}
+
+/// Whether focus is inside the scope element, global shortcuts are always active.
+fn scope_active(scope: &Option<NodeRef>) -> bool {
+    let scope = match scope {
+        Some(scope) => scope,
+        None => return true,
+    };
+    let active = web_sys::window()
+        .and_then(|window| window.document())
+        .and_then(|document| document.active_element());
+    match (scope.get(), active) {
+        (Some(scope), Some(active)) => scope.contains(Some(&active)),
+        _ => false,
+    }
+}
+
+fn typing(event: &KeyboardEvent) -> bool {
+    event
+        .target()
+        .and_then(|target| target.dyn_into::<web_sys::HtmlElement>().ok())
+        .is_some_and(|element| {
+            matches!(element.tag_name().as_str(), "INPUT" | "TEXTAREA" | "SELECT")
+                || element.is_content_editable()
+        })
+}
+
+// only the wasm listener dispatches keys
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+fn on_keydown(event: &Event) {
+    let event = match event.dyn_ref::<KeyboardEvent>() {
+        Some(event) => event,
+        None => return,
+    };
+    let chord = match Chord::from_event(event) {
+        Some(chord) if chord.has_modifier() || !typing(event) => chord,
+        _ => return,
+    };
+    let (action, used) = REGISTRY.with(|registry| {
+        registry
+            .borrow_mut()
+            .press(chord, js_sys::Date::now(), scope_active)
+    });
+    if used {
+        event.prevent_default();
+    }
+    if let Some(action) = action {
+        action();
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn listen() -> Option<EventListener> {
+    let window = web_sys::window()?;
+    Some(EventListener::new_with_options(
+        &window,
+        "keydown",
+        gloo::events::EventListenerOptions::enable_prevent_default(),
+        on_keydown,
+    ))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn listen() -> Option<EventListener> {
+    None
+}
+
+/// Builds a shortcut registration, see `Shortcut::bind`.
+pub struct Shortcut {
+    source: String,
+    description: String,
+    scope: Option<NodeRef>,
+}
+
+impl Shortcut {
+    /// Chords separated by spaces, modifiers joined with `+`: `Ctrl+K`, `g i`, `Shift+?`.
+    pub fn bind(shortcut: impl Into<String>) -> Self {
+        let source = shortcut.into();
+        Shortcut {
+            description: source.clone(),
+            source,
+            scope: None,
+        }
+    }
+
+    /// Shown in the help listing and in conflict errors.
+    pub fn describe(mut self, description: impl Into<String>) -> Self {
+        self.description = description.into();
+        self
+    }
+
+    /// Only trigger while focus is inside the element.
+    pub fn within(mut self, node_ref: &NodeRef) -> Self {
+        self.scope = Some(node_ref.clone());
+        self
+    }
+
+    /// Send the message built by `msg` when the shortcut is pressed.
+    pub fn send<T, M>(
+        self,
+        sender: &MessageSender<T>,
+        msg: impl Fn() -> M + 'static,
+    ) -> Result<ShortcutHandle, ShortcutError>
+    where
+        M: Messenger<Target = T> + 'static,
+        T: 'static,
+    {
+        let sender = sender.clone();
+        self.register(Rc::new(move || msg().send(&sender)))
+    }
+
+    fn register(self, action: Action) -> Result<ShortcutHandle, ShortcutError> {
+        let sequence = self
+            .source
+            .split_whitespace()
+            .map(str::parse)
+            .collect::<Result<Vec<Chord>, _>>()?;
+        if sequence.is_empty() {
+            return Err(ShortcutError::Invalid(self.source));
+        }
+        REGISTRY.with(|registry| {
+            let mut registry = registry.borrow_mut();
+            if let Some(existing) = registry.conflict(&sequence, &self.scope) {
+                return Err(ShortcutError::Conflict {
+                    shortcut: self.source,
+                    existing: existing.description.clone(),
+                });
+            }
+            if registry.listener.is_none() {
+                registry.listener = listen();
+            }
+            let id = registry.next_id;
+            registry.next_id += 1;
+            registry.bindings.push(Binding {
+                id,
+                sequence,
+                description: self.description,
+                scope: self.scope,
+                action,
+            });
+            Ok(ShortcutHandle { id })
+        })
+    }
+}
+
+/// Keeps a shortcut registered, dropping it removes the shortcut.
+pub struct ShortcutHandle {
+    id: u32,
+}
+
+impl Drop for ShortcutHandle {
+    fn drop(&mut self) {
+        REGISTRY.with(|registry| {
+            let mut registry = registry.borrow_mut();
+            registry.bindings.retain(|binding| binding.id != self.id);
+            if registry.bindings.is_empty() {
+                registry.listener.take();
+                registry.pressed.clear();
+            }
+        });
+    }
+}
+
+/// Every registered shortcut with its description, e.g. for a `?` help dialog.
+pub fn help() -> Vec<(String, String)> {
+    REGISTRY.with(|registry| {
+        let mut help: Vec<_> = registry
+            .borrow()
+            .bindings
+            .iter()
+            .map(|binding| {
+                let keys: Vec<_> = binding.sequence.iter().map(Chord::to_string).collect();
+                (keys.join(" "), binding.description.clone())
+            })
+            .collect();
+        help.sort();
+        help
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    fn chord(source: &str) -> Chord {
+        source.parse().unwrap()
+    }
+
+    fn counter(count: &Rc<Cell<u32>>, add: u32) -> Action {
+        let count = count.clone();
+        Rc::new(move || count.set(count.get() + add))
+    }
+
+    fn press(source: &str, now: f64) -> bool {
+        let (action, _) =
+            REGISTRY.with(|registry| registry.borrow_mut().press(chord(source), now, |_| true));
+        action.map(|action| action()).is_some()
+    }
+
+    #[test]
+    fn parses_chords() {
+        let search = chord("Ctrl+Shift+k");
+        assert!(search.ctrl && search.shift && !search.alt);
+        assert_eq!(search.key, "k");
+        assert_eq!(search.to_string(), "Ctrl+Shift+K");
+        assert_eq!(chord("Shift+?"), chord("?"));
+        assert_eq!(chord("Ctrl++").key, "+");
+        assert_eq!(chord("esc").to_string(), "Escape");
+        assert!("Hyper+K".parse::<Chord>().is_err());
+    }
+
+    #[test]
+    fn sequences_conflicts_and_help() {
+        let count = Rc::new(Cell::new(0));
+        let inbox = Shortcut::bind("g i")
+            .describe("Go to inbox")
+            .register(counter(&count, 1))
+            .unwrap();
+        let search = Shortcut::bind("Ctrl+K")
+            .describe("Search")
+            .register(counter(&count, 10))
+            .unwrap();
+        assert_eq!(
+            Shortcut::bind("g")
+                .register(counter(&count, 100))
+                .err()
+                .unwrap(),
+            ShortcutError::Conflict {
+                shortcut: "g".into(),
+                existing: "Go to inbox".into()
+            }
+        );
+
+        assert!(!press("g", 0.0));
+        assert!(press("i", 500.0));
+        assert!(press("ctrl+k", 600.0));
+        assert_eq!(count.get(), 11);
+
+        // too slow, the sequence starts over
+        assert!(!press("g", 1000.0));
+        assert!(!press("i", 3000.0));
+        // an unrelated key in the middle of a sequence restarts it
+        assert!(!press("g", 4000.0));
+        assert!(!press("x", 4100.0));
+        assert!(!press("g", 4200.0));
+        assert!(press("i", 4300.0));
+        assert_eq!(count.get(), 12);
+
+        assert_eq!(
+            help(),
+            vec![
+                ("Ctrl+K".to_string(), "Search".to_string()),
+                ("G I".to_string(), "Go to inbox".to_string())
+            ]
+        );
+        drop(inbox);
+        drop(search);
+        assert!(help().is_empty());
+    }
+}
@@ -0,0 +1,155 @@
+use futures::channel::oneshot;
+use futures::executor::{LocalPool, LocalSpawner};
+use futures::future::LocalBoxFuture;
+use futures::prelude::*;
+use futures::task::{Context, LocalSpawnExt, Poll};
+use std::cell::RefCell;
+use std::pin::Pin;
+use std::rc::Rc;
+
+/// Executor used for every task afterglow spawns: message loops, bus proxies and dispatches.
+pub trait Spawner {
+    fn spawn_local(&self, fut: LocalBoxFuture<'static, ()>);
+}
+
+/// Spawn onto the browser's microtask queue.
+#[cfg(target_arch = "wasm32")]
+#[derive(Clone, Copy, Default)]
+pub struct WasmSpawner;
+
+#[cfg(target_arch = "wasm32")]
+impl Spawner for WasmSpawner {
+    fn spawn_local(&self, fut: LocalBoxFuture<'static, ()>) {
+        wasm_bindgen_futures::spawn_local(fut);
+    }
+}
+
+/// Single threaded executor for running containers outside of the browser.
+///
+/// Nothing runs until `run_until_stalled` or `run` is called.
+#[derive(Clone)]
+pub struct NativeSpawner {
+    pool: Rc<RefCell<LocalPool>>,
+    spawner: LocalSpawner,
+}
+
+thread_local! {
+    static NATIVE: NativeSpawner = NativeSpawner::new();
+    static SPAWNER: RefCell<Option<Rc<dyn Spawner>>> = const { RefCell::new(None) };
+}
+
+impl Default for NativeSpawner {
+    fn default() -> Self {
+        let pool = LocalPool::new();
+        let spawner = pool.spawner();
+        NativeSpawner {
+            pool: Rc::new(RefCell::new(pool)),
+            spawner,
+        }
+    }
+}
+
+impl NativeSpawner {
+    pub fn new() -> Self {
+        NativeSpawner::default()
+    }
+
+    /// The pool used by default on non-wasm targets.
+    pub fn current() -> Self {
+        NATIVE.with(|native| native.clone())
+    }
+
+    /// Run every task until none of them can make progress.
+    pub fn run_until_stalled(&self) {
+        self.pool.borrow_mut().run_until_stalled();
+    }
+
+    /// Run until every task spawned on this pool is done.
+    pub fn run(&self) {
+        self.pool.borrow_mut().run();
+    }
+}
+
+impl Spawner for NativeSpawner {
+    fn spawn_local(&self, fut: LocalBoxFuture<'static, ()>) {
+        self.spawner
+            .spawn_local(fut)
+            .expect("unable to spawn on local pool");
+    }
+}
+
+fn default_spawner() -> Rc<dyn Spawner> {
+    #[cfg(target_arch = "wasm32")]
+    {
+        Rc::new(WasmSpawner)
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        Rc::new(NativeSpawner::current())
+    }
+}
+
+/// Replace the executor for the current thread, tasks already spawned keep running where they are.
+pub fn set_spawner(spawner: impl Spawner + 'static) {
+    SPAWNER.with(|current| current.replace(Some(Rc::new(spawner))));
+}
+
+/// Spawn a detached task on the current spawner.
+pub fn spawn_local<F>(fut: F)
+where
+    F: Future<Output = ()> + 'static,
+{
+    let spawner = SPAWNER.with(|current| {
+        current
+            .borrow_mut()
+            .get_or_insert_with(default_spawner)
+            .clone()
+    });
+    spawner.spawn_local(fut.boxed_local());
+}
+
+/// Spawn a task and get a handle to it's output, dropping the handle detaches the task.
+pub fn spawn<F>(fut: F) -> JoinHandle<F::Output>
+where
+    F: Future + 'static,
+{
+    let (tx, rx) = oneshot::channel();
+    spawn_local(async move {
+        let _ = tx.send(fut.await);
+    });
+    JoinHandle(rx)
+}
+
+/// Resolves to the task's output, or `None` if the spawner dropped it before completion.
+pub struct JoinHandle<T>(oneshot::Receiver<T>);
+
+impl<T> Future for JoinHandle<T> {
+    type Output = Option<T>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.0).poll(cx).map(Result::ok)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    #[test]
+    fn native_spawner_runs_tasks() {
+        let spawner = NativeSpawner::current();
+        let done = Rc::new(Cell::new(false));
+
+        let handle = spawn(async { 42 });
+        let flag = done.clone();
+        spawn_local(async move {
+            assert_eq!(handle.await, Some(42));
+            flag.set(true);
+        });
+
+        assert!(!done.get());
+        spawner.run_until_stalled();
+        assert!(done.get());
+    }
+}
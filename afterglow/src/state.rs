@@ -0,0 +1,137 @@
+use std::cell::{Cell, Ref, RefCell};
+use std::fmt;
+
+/// What a `State` is currently borrowed for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Phase {
+    Idle,
+    /// A messenger is mutating the model.
+    Update,
+    /// A renderer or lifecycle hook is reading the model.
+    Render,
+}
+
+/// Returned when the state is borrowed in a phase that conflicts with the requested one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PhaseError {
+    pub current: Phase,
+    pub requested: Phase,
+}
+
+impl fmt::Display for PhaseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "state is in {:?} phase, unable to enter {:?}",
+            self.current, self.requested
+        )
+    }
+}
+
+impl std::error::Error for PhaseError {}
+
+/// Single threaded storage for a container's model.
+///
+/// Mutation only happens inside `update` and reads inside `render`, so a renderer can never
+/// observe a model halfway through a messenger, and neither side has to wait on a lock.
+pub struct State<T> {
+    data: RefCell<T>,
+    phase: Cell<Phase>,
+}
+
+impl<T> State<T> {
+    pub fn new(data: T) -> Self {
+        State {
+            data: RefCell::new(data),
+            phase: Cell::new(Phase::Idle),
+        }
+    }
+
+    pub fn phase(&self) -> Phase {
+        self.phase.get()
+    }
+
+    /// Mutate the model, fails if it's currently being read or updated.
+    pub fn update<R>(&self, f: impl FnOnce(&mut T) -> R) -> Result<R, PhaseError> {
+        let mut data = self.data.try_borrow_mut().map_err(|_| PhaseError {
+            current: self.phase.get(),
+            requested: Phase::Update,
+        })?;
+        let _phase = PhaseGuard::enter(&self.phase, Phase::Update);
+        Ok(f(&mut data))
+    }
+
+    /// Read the model, fails only while a messenger is updating it.
+    pub fn render<R>(&self, f: impl FnOnce(&T) -> R) -> Result<R, PhaseError> {
+        let data = self.try_borrow().ok_or(PhaseError {
+            current: self.phase.get(),
+            requested: Phase::Render,
+        })?;
+        let _phase = PhaseGuard::enter(&self.phase, Phase::Render);
+        Ok(f(&data))
+    }
+
+    /// Borrow the model for reading outside of a closure.
+    pub fn try_borrow(&self) -> Option<Ref<'_, T>> {
+        self.data.try_borrow().ok()
+    }
+
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
+}
+
+struct PhaseGuard<'a> {
+    phase: &'a Cell<Phase>,
+    previous: Phase,
+}
+
+impl<'a> PhaseGuard<'a> {
+    fn enter(phase: &'a Cell<Phase>, next: Phase) -> Self {
+        let previous = phase.replace(next);
+        PhaseGuard { phase, previous }
+    }
+}
+
+impl Drop for PhaseGuard<'_> {
+    fn drop(&mut self) {
+        self.phase.set(self.previous);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn phases_exclude_each_other() {
+        let state = State::new(1);
+
+        state
+            .render(|value| {
+                assert_eq!(*value, 1);
+                assert_eq!(state.phase(), Phase::Render);
+                assert_eq!(
+                    state.update(|value| *value += 1),
+                    Err(PhaseError {
+                        current: Phase::Render,
+                        requested: Phase::Update
+                    })
+                );
+                // nested reads are fine, e.g. a renderer composing another renderer.
+                assert_eq!(state.render(|value| *value), Ok(1));
+            })
+            .unwrap();
+
+        state
+            .update(|value| {
+                *value += 1;
+                assert_eq!(state.phase(), Phase::Update);
+                assert!(state.render(|_| ()).is_err());
+            })
+            .unwrap();
+
+        assert_eq!(state.phase(), Phase::Idle);
+        assert_eq!(state.into_inner(), 2);
+    }
+}
@@ -0,0 +1,279 @@
+//! Component-local css.
+//!
+//! A renderer returns a `Style` from `Renderer::style`, the css is injected into the document
+//! head once per renderer type when the first container using it is created, and removed after the
+//! last one is dropped. Every `&` starting a selector is replaced by a class unique to the style,
+//! so selectors written under it don't leak into the rest of the page. Strings, comments and
+//! declarations are left alone, as are selectors nested inside a rule, where `&` already means the
+//! parent.
+//!
+//! ```ignore
+//! const STYLE: Style = Style::new("counter", "& { display: flex } &-count { font-weight: bold }");
+//!
+//! impl Renderer for CounterView {
+//!     fn style(&self) -> Option<&'static Style> {
+//!         Some(&STYLE)
+//!     }
+//!
+//!     fn view<'a>(..) -> Node<'a> {
+//!         html!(bump, <div class={STYLE.scope()}><span class={STYLE.class("count")}>..</span></div>)
+//!     }
+//! }
+//! ```
+
+use std::any::TypeId;
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+#[derive(Debug)]
+pub struct Style {
+    name: &'static str,
+    css: &'static str,
+    hash: u32,
+}
+
+/// FNV-1a, usable in constants.
+const fn hash(name: &str, css: &str) -> u32 {
+    let mut hash: u32 = 0x811c_9dc5;
+    let mut i = 0;
+    while i < name.len() {
+        hash ^= name.as_bytes()[i] as u32;
+        hash = hash.wrapping_mul(0x0100_0193);
+        i += 1;
+    }
+    let mut i = 0;
+    while i < css.len() {
+        hash ^= css.as_bytes()[i] as u32;
+        hash = hash.wrapping_mul(0x0100_0193);
+        i += 1;
+    }
+    hash
+}
+
+impl Style {
+    /// `name` only makes the generated class readable, styles are told apart by their content.
+    pub const fn new(name: &'static str, css: &'static str) -> Self {
+        Style {
+            name,
+            css,
+            hash: hash(name, css),
+        }
+    }
+
+    /// The class `&` stands for.
+    pub fn scope(&self) -> String {
+        format!("{}-{:08x}", self.name, self.hash)
+    }
+
+    /// The class `&-suffix` stands for.
+    pub fn class(&self, suffix: &str) -> String {
+        format!("{}-{}", self.scope(), suffix)
+    }
+
+    /// The css with the scope filled in.
+    pub fn css(&self) -> String {
+        scope_selectors(self.css, &self.scope())
+    }
+
+    /// Make sure the css is in the document for as long as the handle lives, injected once for
+    /// every renderer type using it, given by `renderer`.
+    pub fn attach(&'static self, renderer: TypeId) -> StyleHandle {
+        let key = (renderer, self.hash);
+        STYLES.with(|styles| {
+            let mut styles = styles.borrow_mut();
+            let injected = styles.entry(key).or_insert_with(|| Injected {
+                count: 0,
+                element: inject(self),
+            });
+            injected.count += 1;
+        });
+        StyleHandle { key }
+    }
+
+    /// How many live handles use this style, across renderer types.
+    pub fn users(&self) -> usize {
+        STYLES.with(|styles| {
+            let styles = styles.borrow();
+            let users = styles.iter().filter(|((_, hash), _)| *hash == self.hash);
+            users.map(|(_, injected)| injected.count).sum()
+        })
+    }
+}
+
+/// At-rules whose blocks hold rules rather than declarations.
+const GROUPING_RULES: &[&str] = &["media", "supports", "container", "layer", "document"];
+
+/// Replace `&` with the scope's class where it starts a selector.
+fn scope_selectors(css: &str, scope: &str) -> String {
+    let mut out = String::with_capacity(css.len());
+    // whether each open block holds declarations, `&` is only replaced outside of those
+    let mut blocks: Vec<bool> = vec![];
+    // where the text before the next `{` starts in `out`
+    let mut prelude = 0;
+    let mut chars = css.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' | '\'' => {
+                out.push(c);
+                while let Some(next) = chars.next() {
+                    out.push(next);
+                    if next == '\\' {
+                        out.extend(chars.next());
+                    } else if next == c {
+                        break;
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                let leading = out[prelude..].trim().is_empty();
+                out.push(c);
+                out.extend(chars.next());
+                let mut previous = ' ';
+                for next in chars.by_ref() {
+                    out.push(next);
+                    if previous == '*' && next == '/' {
+                        break;
+                    }
+                    previous = next;
+                }
+                // a comment before a selector or at-rule isn't part of it
+                if leading {
+                    prelude = out.len();
+                }
+            }
+            '{' => {
+                let declarations = match out[prelude..].trim_start().strip_prefix('@') {
+                    Some(rule) => {
+                        let mut name = rule.split(|c: char| !c.is_alphanumeric() && c != '-');
+                        !GROUPING_RULES.contains(&name.next().unwrap_or(""))
+                    }
+                    None => true,
+                };
+                blocks.push(declarations);
+                out.push(c);
+                prelude = out.len();
+            }
+            '}' | ';' => {
+                if c == '}' {
+                    blocks.pop();
+                }
+                out.push(c);
+                prelude = out.len();
+            }
+            '&' if !blocks.last().copied().unwrap_or(false)
+                && !out[prelude..].trim_start().starts_with('@') =>
+            {
+                out.push('.');
+                out.push_str(scope);
+            }
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+struct Injected {
+    count: usize,
+    element: Option<web_sys::Element>,
+}
+
+thread_local! {
+    static STYLES: RefCell<HashMap<(TypeId, u32), Injected>> = RefCell::new(HashMap::new());
+}
+
+#[cfg(target_arch = "wasm32")]
+fn inject(style: &Style) -> Option<web_sys::Element> {
+    let document = web_sys::window()?.document()?;
+    let element = document.create_element("style").ok()?;
+    element
+        .set_attribute("data-afterglow", &style.scope())
+        .ok()?;
+    element.set_text_content(Some(&style.css()));
+    document.head()?.append_child(&element).ok()?;
+    Some(element)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn inject(_style: &Style) -> Option<web_sys::Element> {
+    None
+}
+
+/// Keeps a style injected, see `Style::attach`.
+pub struct StyleHandle {
+    key: (TypeId, u32),
+}
+
+impl Drop for StyleHandle {
+    fn drop(&mut self) {
+        STYLES.with(|styles| {
+            let mut styles = styles.borrow_mut();
+            if let Some(injected) = styles.get_mut(&self.key) {
+                injected.count -= 1;
+                if injected.count == 0 {
+                    if let Some(element) = styles.remove(&self.key).and_then(|i| i.element) {
+                        element.remove();
+                    }
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CARD: Style = Style::new("card", "& { padding: 1em } &-title, & h2 { margin: 0 }");
+
+    #[test]
+    fn scopes_css() {
+        let scope = CARD.scope();
+        assert!(scope.starts_with("card-"));
+        assert_eq!(CARD.class("title"), format!("{}-title", scope));
+        assert_eq!(
+            CARD.css(),
+            format!(
+                ".{0} {{ padding: 1em }} .{0}-title, .{0} h2 {{ margin: 0 }}",
+                scope
+            )
+        );
+        assert_ne!(scope, Style::new("card", "& { padding: 2em }").scope());
+    }
+
+    #[test]
+    fn only_scopes_selectors() {
+        let css = Style::new(
+            "icon",
+            "&::before { content: \"&\"; background: url(a.svg?x=1&y=2) } \
+             /* & */ @media (max-width: 600px) { &[title='&'], & + & { margin: 0 } } \
+             @font-face { src: url(f.woff?a&b) }",
+        );
+        let scope = css.scope();
+        assert_eq!(
+            css.css(),
+            format!(
+                ".{0}::before {{ content: \"&\"; background: url(a.svg?x=1&y=2) }} \
+                 /* & */ @media (max-width: 600px) {{ .{0}[title='&'], .{0} + .{0} {{ margin: 0 }} }} \
+                 @font-face {{ src: url(f.woff?a&b) }}",
+                scope
+            )
+        );
+    }
+
+    struct Card;
+    struct Sidebar;
+
+    #[test]
+    fn counts_users() {
+        let first = CARD.attach(TypeId::of::<Card>());
+        let second = CARD.attach(TypeId::of::<Card>());
+        let other = CARD.attach(TypeId::of::<Sidebar>());
+        assert_eq!(CARD.users(), 3);
+        STYLES.with(|styles| assert_eq!(styles.borrow().len(), 2));
+        drop(first);
+        drop(other);
+        assert_eq!(CARD.users(), 1);
+        drop(second);
+        assert_eq!(CARD.users(), 0);
+    }
+}
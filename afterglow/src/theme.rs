@@ -0,0 +1,246 @@
+//! Design tokens shared by every renderer.
+//!
+//! Renderers read the current theme with `theme()`, which also remembers the container being
+//! rendered so it's redrawn once the theme changes. Every `Entry` root carries the tokens as css
+//! custom properties, e.g. `var(--ag-color-primary)`, so plain stylesheets can follow along.
+//!
+//! Switch themes with `EntryMessage::SetTheme`, or from any container with the `SetTheme`
+//! messenger.
+
+use crate::prelude::*;
+use crate::renderer::Dependents;
+use crate::vnode::Str;
+use std::cell::RefCell;
+use std::marker::PhantomData;
+use std::rc::Rc;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Colors {
+    pub primary: Str,
+    pub secondary: Str,
+    pub background: Str,
+    pub surface: Str,
+    pub text: Str,
+    pub muted: Str,
+    pub error: Str,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Spacing {
+    pub xs: Str,
+    pub sm: Str,
+    pub md: Str,
+    pub lg: Str,
+    pub xl: Str,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Typography {
+    pub font_family: Str,
+    pub monospace: Str,
+    pub font_size: Str,
+    pub line_height: Str,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Theme {
+    /// Set as `data-theme` on the entry roots.
+    pub name: Str,
+    pub colors: Colors,
+    pub spacing: Spacing,
+    pub typography: Typography,
+}
+
+impl Theme {
+    pub fn light() -> Self {
+        Theme {
+            name: "light".into(),
+            colors: Colors {
+                primary: "#3273dc".into(),
+                secondary: "#00d1b2".into(),
+                background: "#ffffff".into(),
+                surface: "#f5f5f5".into(),
+                text: "#363636".into(),
+                muted: "#7a7a7a".into(),
+                error: "#f14668".into(),
+            },
+            spacing: Spacing::default(),
+            typography: Typography::default(),
+        }
+    }
+
+    pub fn dark() -> Self {
+        Theme {
+            name: "dark".into(),
+            colors: Colors {
+                primary: "#6a9fff".into(),
+                secondary: "#1de9b6".into(),
+                background: "#121212".into(),
+                surface: "#1e1e1e".into(),
+                text: "#e0e0e0".into(),
+                muted: "#9e9e9e".into(),
+                error: "#ff6e7f".into(),
+            },
+            spacing: Spacing::default(),
+            typography: Typography::default(),
+        }
+    }
+
+    /// The tokens as css custom properties.
+    pub fn properties(&self) -> Vec<(&'static str, &str)> {
+        let Colors {
+            primary,
+            secondary,
+            background,
+            surface,
+            text,
+            muted,
+            error,
+        } = &self.colors;
+        let Spacing { xs, sm, md, lg, xl } = &self.spacing;
+        let Typography {
+            font_family,
+            monospace,
+            font_size,
+            line_height,
+        } = &self.typography;
+        vec![
+            ("--ag-color-primary", primary),
+            ("--ag-color-secondary", secondary),
+            ("--ag-color-background", background),
+            ("--ag-color-surface", surface),
+            ("--ag-color-text", text),
+            ("--ag-color-muted", muted),
+            ("--ag-color-error", error),
+            ("--ag-space-xs", xs),
+            ("--ag-space-sm", sm),
+            ("--ag-space-md", md),
+            ("--ag-space-lg", lg),
+            ("--ag-space-xl", xl),
+            ("--ag-font-family", font_family),
+            ("--ag-font-monospace", monospace),
+            ("--ag-font-size", font_size),
+            ("--ag-line-height", line_height),
+        ]
+        .into_iter()
+        .map(|(name, value)| (name, value.as_ref()))
+        .collect()
+    }
+
+    /// Write the tokens onto an element, usually an entry root.
+    pub fn apply(&self, element: &web_sys::HtmlElement) {
+        let style = element.style();
+        for (name, value) in self.properties() {
+            let _ = style.set_property(name, value);
+        }
+        let _ = element.set_attribute("data-theme", &self.name);
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::light()
+    }
+}
+
+impl Default for Spacing {
+    fn default() -> Self {
+        Spacing {
+            xs: "0.25rem".into(),
+            sm: "0.5rem".into(),
+            md: "1rem".into(),
+            lg: "1.5rem".into(),
+            xl: "3rem".into(),
+        }
+    }
+}
+
+impl Default for Typography {
+    fn default() -> Self {
+        Typography {
+            font_family: "system-ui, -apple-system, \"Segoe UI\", Roboto, sans-serif".into(),
+            monospace: "ui-monospace, Menlo, Consolas, monospace".into(),
+            font_size: "16px".into(),
+            line_height: "1.5".into(),
+        }
+    }
+}
+
+thread_local! {
+    static CURRENT: RefCell<Rc<Theme>> = RefCell::new(Rc::new(Theme::default()));
+    static DEPENDENTS: RefCell<Dependents> = RefCell::new(Dependents::default());
+    static ROOTS: RefCell<Vec<web_sys::HtmlElement>> = const { RefCell::new(Vec::new()) };
+}
+
+/// The current theme, the container being rendered is redrawn when it changes.
+pub fn theme() -> Rc<Theme> {
+    DEPENDENTS.with(|dependents| dependents.borrow_mut().track());
+    CURRENT.with(|current| current.borrow().clone())
+}
+
+/// Replace the current theme and invalidate every container that used the previous one.
+///
+/// The new tokens are written to the entry roots right away, the containers are redrawn on the
+/// next render.
+pub fn set_theme(theme: Theme) {
+    ROOTS.with(|roots| roots.borrow().iter().for_each(|root| theme.apply(root)));
+    CURRENT.with(|current| current.replace(Rc::new(theme)));
+    DEPENDENTS.with(|dependents| dependents.borrow_mut().invalidate());
+}
+
+pub(crate) fn add_root(root: &web_sys::HtmlElement) {
+    CURRENT.with(|current| current.borrow().apply(root));
+    ROOTS.with(|roots| roots.borrow_mut().push(root.clone()));
+}
+
+pub(crate) fn remove_root(root: &web_sys::HtmlElement) {
+    ROOTS.with(|roots| roots.borrow_mut().retain(|known| known != root));
+}
+
+/// Switch the theme from any container, the container rerenders and so do all dependents.
+pub struct SetTheme<T> {
+    theme: Theme,
+    target: PhantomData<fn(&mut T)>,
+}
+
+impl<T> SetTheme<T> {
+    pub fn new(theme: Theme) -> Self {
+        SetTheme {
+            theme,
+            target: PhantomData,
+        }
+    }
+}
+
+impl<T> Messenger for SetTheme<T> {
+    type Target = T;
+
+    fn update(
+        self: Box<Self>,
+        _target: &mut Self::Target,
+        _sender: &MessageSender<Self::Target>,
+        _render_tx: &Sender<((), oneshot::Sender<()>)>,
+    ) -> bool {
+        set_theme(self.theme);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn switches_theme() {
+        let (sender, _) = mpsc::unbounded();
+        let (render_tx, _) = mpsc::unbounded();
+        assert_eq!(theme().name, "light");
+
+        assert!(Box::new(SetTheme::new(Theme::dark())).update(&mut (), &sender, &render_tx));
+        let dark = theme();
+        assert_eq!(dark.name, "dark");
+        assert!(dark
+            .properties()
+            .contains(&("--ag-color-background", "#121212")));
+    }
+}
@@ -0,0 +1,434 @@
+//! Enter and leave transitions driven by CSS classes.
+//!
+//! dodrio removes nodes as soon as they're missing from a render, so items that should animate
+//! out stay in a `Transitions` list until their leave transition is done. Every item goes through
+//! these stages, each rendered with the classes of a `Transition` named e.g. `fade`:
+//!
+//! | stage        | classes                        |
+//! |--------------|--------------------------------|
+//! | `EnterStart` | `fade-enter fade-enter-active` |
+//! | `Entering`   | `fade-enter-active`            |
+//! | `Entered`    |                                |
+//! | `LeaveStart` | `fade-leave-active`            |
+//! | `Leaving`    | `fade-leave fade-leave-active` |
+//!
+//! `-enter` and `-leave` hold the hidden state, the `-active` classes the `transition` itself:
+//!
+//! ```css
+//! .fade-enter-active, .fade-leave-active { transition: opacity 300ms; }
+//! .fade-enter, .fade-leave { opacity: 0; }
+//! ```
+//!
+//! Items are added and removed with `TransitionMsg`, which also steps them through the stages.
+
+use crate::prelude::*;
+use dodrio::bumpalo::Bump;
+
+/// A named set of transition classes and how long the transition takes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Transition {
+    pub name: &'static str,
+    /// Milliseconds, should match the `transition-duration` of the css.
+    pub duration: u32,
+}
+
+/// Time for the browser to paint the start classes before they're swapped.
+const FRAME: u32 = 20;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stage {
+    EnterStart,
+    Entering,
+    Entered,
+    LeaveStart,
+    Leaving,
+}
+
+impl Stage {
+    /// The stage that follows, `None` once the item should be removed.
+    pub fn next(self) -> Option<Stage> {
+        match self {
+            Stage::EnterStart => Some(Stage::Entering),
+            Stage::Entering | Stage::Entered => Some(Stage::Entered),
+            Stage::LeaveStart => Some(Stage::Leaving),
+            Stage::Leaving => None,
+        }
+    }
+
+    pub fn is_leaving(self) -> bool {
+        matches!(self, Stage::LeaveStart | Stage::Leaving)
+    }
+}
+
+impl Transition {
+    pub const fn new(name: &'static str, duration: u32) -> Self {
+        Transition { name, duration }
+    }
+
+    /// Classes for an element in the given stage.
+    pub fn classes(&self, stage: Stage) -> String {
+        let name = self.name;
+        match stage {
+            Stage::EnterStart => format!("{0}-enter {0}-enter-active", name),
+            Stage::Entering => format!("{}-enter-active", name),
+            Stage::Entered => String::new(),
+            Stage::LeaveStart => format!("{}-leave-active", name),
+            Stage::Leaving => format!("{0}-leave {0}-leave-active", name),
+        }
+    }
+
+    /// Same as `classes`, allocated in the render's bump.
+    pub fn class<'a>(&self, bump: &'a Bump, stage: Stage) -> &'a str {
+        let name = self.name;
+        match stage {
+            Stage::EnterStart => bf!(in bump, "{0}-enter {0}-enter-active", name),
+            Stage::Entering => bf!(in bump, "{}-enter-active", name),
+            Stage::Entered => return "",
+            Stage::LeaveStart => bf!(in bump, "{}-leave-active", name),
+            Stage::Leaving => bf!(in bump, "{0}-leave {0}-leave-active", name),
+        }
+        .into_bump_str()
+    }
+
+    /// How long an item stays in a stage before moving on.
+    fn delay(&self, stage: Stage) -> u32 {
+        match stage {
+            Stage::EnterStart | Stage::LeaveStart => FRAME,
+            _ => self.duration,
+        }
+    }
+
+    /// Animate an element entering outside of a render, e.g. a whole container.
+    pub async fn enter(&self, element: &web_sys::Element) {
+        // clears what an earlier `leave` left on the element
+        let stages = [Stage::EnterStart, Stage::Entering, Stage::Entered];
+        self.run(element, Stage::Leaving, stages).await;
+    }
+
+    /// Animate an element leaving, resolves once it can be removed.
+    pub async fn leave(&self, element: &web_sys::Element) {
+        self.run(element, Stage::Entered, [Stage::LeaveStart, Stage::Leaving])
+            .await;
+    }
+
+    async fn run<const N: usize>(
+        &self,
+        element: &web_sys::Element,
+        mut current: Stage,
+        stages: [Stage; N],
+    ) {
+        for stage in stages {
+            self.swap(element, current, stage);
+            current = stage;
+            sleep(self.delay(stage)).await;
+        }
+    }
+
+    fn swap(&self, element: &web_sys::Element, from: Stage, to: Stage) {
+        let classes = element.class_list();
+        for class in self.classes(from).split_whitespace() {
+            let _ = classes.remove_1(class);
+        }
+        for class in self.classes(to).split_whitespace() {
+            let _ = classes.add_1(class);
+        }
+    }
+}
+
+/// Wait on the browser's timer, native targets don't wait at all.
+async fn sleep(#[cfg_attr(not(target_arch = "wasm32"), allow(unused_variables))] millis: u32) {
+    #[cfg(target_arch = "wasm32")]
+    gloo::timers::future::TimeoutFuture::new(millis).await;
+}
+
+struct Item<K, V> {
+    key: K,
+    value: V,
+    stage: Stage,
+    /// Bumped whenever the item restarts, so steps scheduled before are ignored.
+    generation: u32,
+}
+
+/// Keyed items that animate when added or removed.
+pub struct Transitions<K, V> {
+    transition: Transition,
+    items: Vec<Item<K, V>>,
+}
+
+impl<K: PartialEq, V> Transitions<K, V> {
+    pub fn new(transition: Transition) -> Self {
+        Transitions {
+            transition,
+            items: vec![],
+        }
+    }
+
+    /// Start with items that are already shown and don't animate in.
+    pub fn with_items(transition: Transition, items: impl IntoIterator<Item = (K, V)>) -> Self {
+        let items = items
+            .into_iter()
+            .map(|(key, value)| Item {
+                key,
+                value,
+                stage: Stage::Entered,
+                generation: 0,
+            })
+            .collect();
+        Transitions { transition, items }
+    }
+
+    pub fn transition(&self) -> Transition {
+        self.transition
+    }
+
+    /// Every item to render with it's stage, leaving ones included.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V, Stage)> {
+        self.items
+            .iter()
+            .map(|item| (&item.key, &item.value, item.stage))
+    }
+
+    /// Items that aren't leaving.
+    pub fn values(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.iter()
+            .filter(|(_, _, stage)| !stage.is_leaving())
+            .map(|(key, value, _)| (key, value))
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.items
+            .iter()
+            .find(|item| item.key == *key && !item.stage.is_leaving())
+            .map(|item| &item.value)
+    }
+
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.items
+            .iter_mut()
+            .find(|item| item.key == *key && !item.stage.is_leaving())
+            .map(|item| &mut item.value)
+    }
+
+    pub fn stage(&self, key: &K) -> Option<Stage> {
+        self.position(key).map(|index| self.items[index].stage)
+    }
+
+    fn position(&self, key: &K) -> Option<usize> {
+        self.items.iter().position(|item| item.key == *key)
+    }
+
+    /// Add an item before the `index`th item that isn't leaving, returns it's generation.
+    ///
+    /// An item still leaving under the same key is replaced and enters again.
+    pub fn insert(&mut self, index: usize, key: K, value: V) -> u32 {
+        let generation = match self.position(&key) {
+            Some(existing) => self.items.remove(existing).generation + 1,
+            None => 0,
+        };
+        let position = self
+            .items
+            .iter()
+            .enumerate()
+            .filter(|(_, item)| !item.stage.is_leaving())
+            .map(|(position, _)| position)
+            .nth(index)
+            .unwrap_or(self.items.len());
+        self.items.insert(
+            position,
+            Item {
+                key,
+                value,
+                stage: Stage::EnterStart,
+                generation,
+            },
+        );
+        generation
+    }
+
+    pub fn push(&mut self, key: K, value: V) -> u32 {
+        self.insert(usize::MAX, key, value)
+    }
+
+    /// Start the leave transition, returns the generation or `None` if there's nothing to remove.
+    pub fn remove(&mut self, key: &K) -> Option<u32> {
+        let item = self
+            .items
+            .iter_mut()
+            .find(|item| item.key == *key && !item.stage.is_leaving())?;
+        item.stage = Stage::LeaveStart;
+        item.generation += 1;
+        Some(item.generation)
+    }
+
+    /// Move an item to it's next stage, returns whether it changed.
+    ///
+    /// Steps for an older generation are ignored.
+    pub fn advance(&mut self, key: &K, generation: u32) -> bool {
+        let index = match self.position(key) {
+            Some(index) if self.items[index].generation == generation => index,
+            _ => return false,
+        };
+        let item = &mut self.items[index];
+        match item.stage.next() {
+            Some(stage) if stage == item.stage => false,
+            Some(stage) => {
+                item.stage = stage;
+                true
+            }
+            None => {
+                self.items.remove(index);
+                true
+            }
+        }
+    }
+}
+
+enum Action<V> {
+    Insert(usize, V),
+    Remove,
+    Advance(u32),
+}
+
+/// Changes to a `Transitions` list owned by `H`.
+pub struct TransitionMsg<H, K, V> {
+    select: fn(&mut H) -> &mut Transitions<K, V>,
+    key: K,
+    action: Action<V>,
+}
+
+impl<H, K, V> TransitionMsg<H, K, V> {
+    pub fn insert(
+        select: fn(&mut H) -> &mut Transitions<K, V>,
+        index: usize,
+        key: K,
+        value: V,
+    ) -> Self {
+        TransitionMsg {
+            select,
+            key,
+            action: Action::Insert(index, value),
+        }
+    }
+
+    pub fn push(select: fn(&mut H) -> &mut Transitions<K, V>, key: K, value: V) -> Self {
+        TransitionMsg::insert(select, usize::MAX, key, value)
+    }
+
+    pub fn remove(select: fn(&mut H) -> &mut Transitions<K, V>, key: K) -> Self {
+        TransitionMsg {
+            select,
+            key,
+            action: Action::Remove,
+        }
+    }
+}
+
+impl<H, K, V> Messenger for TransitionMsg<H, K, V>
+where
+    H: 'static,
+    K: PartialEq + Clone + 'static,
+    V: 'static,
+{
+    type Target = H;
+
+    fn update(
+        self: Box<Self>,
+        target: &mut Self::Target,
+        sender: &MessageSender<Self::Target>,
+        render_tx: &Sender<((), oneshot::Sender<()>)>,
+    ) -> bool {
+        let TransitionMsg {
+            select,
+            key,
+            action,
+        } = *self;
+        let list = select(target);
+        let generation = match action {
+            Action::Insert(index, value) => list.insert(index, key.clone(), value),
+            Action::Remove => match list.remove(&key) {
+                Some(generation) => generation,
+                None => return false,
+            },
+            Action::Advance(generation) => {
+                if !list.advance(&key, generation) {
+                    return false;
+                }
+                generation
+            }
+        };
+        let stage = match list.stage(&key) {
+            Some(stage) if stage != Stage::Entered => stage,
+            // done, nothing left to schedule
+            _ => return true,
+        };
+        let delay = list.transition.delay(stage);
+        let sender = sender.clone();
+        let mut render_tx = render_tx.clone();
+        // step once `Entry` patched the current classes into the document and they had time to
+        // apply, without an entry rendering (e.g. in tests) the steps only follow the delays
+        spawn_local(async move {
+            let (tx, rx) = oneshot::channel();
+            let _ = render_tx.send(((), tx)).await;
+            let _ = rx.await;
+            sleep(delay).await;
+            TransitionMsg {
+                select,
+                key,
+                action: Action::Advance(generation),
+            }
+            .send(&sender);
+        });
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FADE: Transition = Transition::new("fade", 300);
+
+    #[test]
+    fn stage_classes() {
+        assert_eq!(
+            FADE.classes(Stage::EnterStart),
+            "fade-enter fade-enter-active"
+        );
+        assert_eq!(FADE.classes(Stage::Entered), "");
+        let bump = Bump::new();
+        assert_eq!(
+            FADE.class(&bump, Stage::Leaving),
+            "fade-leave fade-leave-active"
+        );
+    }
+
+    #[test]
+    fn items_leave_after_their_transition() {
+        let mut list = Transitions::with_items(FADE, vec![(1, "a"), (2, "b")]);
+        let generation = list.insert(1, 3, "c");
+        assert_eq!(
+            list.iter().map(|(key, _, _)| *key).collect::<Vec<_>>(),
+            vec![1, 3, 2]
+        );
+        assert!(list.advance(&3, generation));
+        assert!(list.advance(&3, generation));
+        assert!(!list.advance(&3, generation));
+        assert_eq!(list.stage(&3), Some(Stage::Entered));
+
+        let leaving = list.remove(&1).unwrap();
+        assert_eq!(list.get(&1), None);
+        assert_eq!(list.values().count(), 2);
+        // stale steps are ignored
+        assert!(!list.advance(&1, leaving - 1));
+        assert!(list.advance(&1, leaving));
+        assert!(list.advance(&1, leaving));
+        assert_eq!(list.iter().count(), 2);
+
+        // coming back while leaving restarts the enter transition
+        let leaving = list.remove(&2).unwrap();
+        let entering = list.push(2, "b");
+        assert!(!list.advance(&2, leaving));
+        assert_eq!(list.stage(&2), Some(Stage::EnterStart));
+        assert!(list.advance(&2, entering));
+    }
+}
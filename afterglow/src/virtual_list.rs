@@ -0,0 +1,369 @@
+//! Windowed rendering for long lists and grids.
+//!
+//! A model keeps a `VirtualList` next to it's items, the renderer calls `VirtualList::view` with a
+//! closure building one item at a time. Only the rows inside the viewport, plus `overscan` rows on
+//! each side, are built. Scrolling sends a `VirtualMsg` that only rerenders once the visible rows
+//! change.
+//!
+//! ```ignore
+//! fn list(model: &mut Model) -> &mut VirtualList {
+//!     &mut model.list
+//! }
+//!
+//! target.list.view(bump, list, sender, |index| {
+//!     html!(bump, <div class="row">{ target.rows[index].as_str() }</div>)
+//! })
+//! ```
+
+use crate::events;
+use crate::prelude::*;
+use crate::vnode::Str;
+use dodrio::builder;
+use dodrio::bumpalo::{self, Bump};
+use std::ops::Range;
+
+/// Height of every row in pixels.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RowHeights {
+    Fixed(f64),
+    /// Height of each row, rows past the end use the last one.
+    Variable(Vec<f64>),
+}
+
+/// Window math and scroll state of a virtualized list.
+#[derive(Clone, Debug)]
+pub struct VirtualList {
+    id: Str,
+    len: usize,
+    columns: usize,
+    heights: RowHeights,
+    /// Start offset of each row for variable heights, with the total height last.
+    offsets: Vec<f64>,
+    overscan: usize,
+    scroll_top: f64,
+    viewport: f64,
+}
+
+impl VirtualList {
+    /// `id` tells lists apart in the document, `viewport` is the initial height in pixels.
+    pub fn new(id: impl Into<Str>, len: usize, heights: RowHeights, viewport: f64) -> Self {
+        let mut list = VirtualList {
+            id: id.into(),
+            len,
+            columns: 1,
+            heights,
+            offsets: vec![],
+            overscan: 3,
+            scroll_top: 0.0,
+            viewport,
+        };
+        list.layout();
+        list
+    }
+
+    /// Rows rendered above and below the viewport.
+    pub fn overscan(mut self, rows: usize) -> Self {
+        self.overscan = rows;
+        self
+    }
+
+    /// Lay items out in a grid, each row holding `columns` items.
+    pub fn columns(mut self, columns: usize) -> Self {
+        self.columns = columns.max(1);
+        self.layout();
+        self
+    }
+
+    pub fn set_len(&mut self, len: usize) {
+        self.len = len;
+        self.layout();
+    }
+
+    pub fn set_heights(&mut self, heights: RowHeights) {
+        self.heights = heights;
+        self.layout();
+    }
+
+    fn layout(&mut self) {
+        self.offsets.clear();
+        if let RowHeights::Variable(heights) = &self.heights {
+            let last = heights.last().copied().unwrap_or_default();
+            let mut offset = 0.0;
+            for row in 0..self.rows() {
+                self.offsets.push(offset);
+                offset += heights.get(row).copied().unwrap_or(last);
+            }
+            self.offsets.push(offset);
+        }
+    }
+
+    pub fn rows(&self) -> usize {
+        self.len.div_ceil(self.columns)
+    }
+
+    /// Offset of a row from the top of the list.
+    pub fn offset(&self, row: usize) -> f64 {
+        let row = row.min(self.rows());
+        match &self.heights {
+            RowHeights::Fixed(height) => row as f64 * height,
+            RowHeights::Variable(_) => self.offsets[row],
+        }
+    }
+
+    pub fn total_height(&self) -> f64 {
+        self.offset(self.rows())
+    }
+
+    /// The row displayed at `offset`.
+    pub fn row_at(&self, offset: f64) -> usize {
+        if self.rows() == 0 || offset <= 0.0 {
+            return 0;
+        }
+        let row = match &self.heights {
+            RowHeights::Fixed(height) if *height > 0.0 => (offset / height) as usize,
+            RowHeights::Fixed(_) => 0,
+            RowHeights::Variable(_) => {
+                let rows = &self.offsets[..self.rows()];
+                rows.partition_point(|start| *start <= offset) - 1
+            }
+        };
+        row.min(self.rows() - 1)
+    }
+
+    /// Rows to build, overscan included.
+    pub fn visible_rows(&self) -> Range<usize> {
+        if self.rows() == 0 {
+            return 0..0;
+        }
+        let first = self.row_at(self.scroll_top);
+        let last = self.row_at(self.scroll_top + self.viewport);
+        first.saturating_sub(self.overscan)..(last + 1 + self.overscan).min(self.rows())
+    }
+
+    /// Items to build, overscan included.
+    pub fn visible_items(&self) -> Range<usize> {
+        let rows = self.visible_rows();
+        (rows.start * self.columns).min(self.len)..(rows.end * self.columns).min(self.len)
+    }
+
+    /// Record a scroll position, returns whether other rows became visible.
+    pub fn set_scroll(&mut self, top: f64, viewport: f64) -> bool {
+        let before = self.visible_rows();
+        self.scroll_top = top.max(0.0);
+        self.viewport = viewport;
+        self.visible_rows() != before
+    }
+
+    /// Scroll position that shows the item at the top of the viewport.
+    pub fn scroll_to_index(&mut self, index: usize) -> f64 {
+        let top = self
+            .offset(index / self.columns)
+            .min((self.total_height() - self.viewport).max(0.0));
+        self.scroll_top = top;
+        top
+    }
+
+    /// Build the scrolling viewport with the visible items.
+    pub fn view<'a, H: 'static>(
+        &self,
+        bump: &'a Bump,
+        select: fn(&mut H) -> &mut VirtualList,
+        sender: &MessageSender<H>,
+        item: impl Fn(usize) -> Node<'a>,
+    ) -> Node<'a> {
+        let rows = self.visible_rows();
+        let columns = self.columns;
+        let mut children = bumpalo::collections::Vec::new_in(bump);
+        for row in rows.clone() {
+            let start = row * columns;
+            let end = (start + columns).min(self.len);
+            let mut node = builder::div(bump)
+                .key(row as u32)
+                .attr("data-row", bf!(in bump, "{}", row).into_bump_str());
+            if columns > 1 {
+                node = node.attr(
+                    "style",
+                    bf!(in bump, "display: grid; grid-template-columns: repeat({}, 1fr)", columns)
+                        .into_bump_str(),
+                );
+            }
+            children.push(
+                (start..end)
+                    .fold(node, |node, index| node.child(item(index)))
+                    .finish(),
+            );
+        }
+
+        let viewport = builder::div(bump)
+            .attr(
+                "data-ag-virtual",
+                bumpalo::collections::String::from_str_in(&self.id, bump).into_bump_str(),
+            )
+            .attr(
+                "style",
+                bf!(in bump, "overflow-y: auto; position: relative; height: {}px", self.viewport)
+                    .into_bump_str(),
+            )
+            .on(
+                "scroll",
+                events::ListenerOptions::new().passive().on(
+                    move |event: Event| {
+                        let element = event.target()?.dyn_into::<web_sys::Element>().ok()?;
+                        Some(VirtualMsg::scroll(
+                            select,
+                            element.scroll_top() as f64,
+                            element.client_height() as f64,
+                        ))
+                    },
+                    sender,
+                ),
+            );
+        let content = builder::div(bump)
+            .attr(
+                "style",
+                bf!(in bump, "position: relative; height: {}px", self.total_height())
+                    .into_bump_str(),
+            )
+            .child(
+                builder::div(bump)
+                    .attr(
+                        "style",
+                        bf!(in bump, "position: absolute; left: 0; right: 0; top: {}px", self.offset(rows.start))
+                            .into_bump_str(),
+                    )
+                    .children(children)
+                    .finish(),
+            )
+            .finish();
+        viewport.child(content).finish()
+    }
+}
+
+enum Action {
+    Scroll { top: f64, viewport: f64 },
+    ScrollTo(usize),
+}
+
+/// Scroll updates for a `VirtualList` owned by `H`.
+pub struct VirtualMsg<H> {
+    select: fn(&mut H) -> &mut VirtualList,
+    action: Action,
+}
+
+impl<H> VirtualMsg<H> {
+    pub fn scroll(select: fn(&mut H) -> &mut VirtualList, top: f64, viewport: f64) -> Self {
+        VirtualMsg {
+            select,
+            action: Action::Scroll { top, viewport },
+        }
+    }
+
+    /// Scroll the list until the item is at the top of the viewport.
+    pub fn scroll_to(select: fn(&mut H) -> &mut VirtualList, index: usize) -> Self {
+        VirtualMsg {
+            select,
+            action: Action::ScrollTo(index),
+        }
+    }
+}
+
+impl<H> Messenger for VirtualMsg<H> {
+    type Target = H;
+
+    fn update(
+        self: Box<Self>,
+        target: &mut Self::Target,
+        _sender: &MessageSender<Self::Target>,
+        render_tx: &Sender<((), oneshot::Sender<()>)>,
+    ) -> bool {
+        let list = (self.select)(target);
+        match self.action {
+            Action::Scroll { top, viewport } => list.set_scroll(top, viewport),
+            Action::ScrollTo(index) => {
+                let top = list.scroll_to_index(index);
+                let selector = format!("[data-ag-virtual=\"{}\"]", list.id);
+                scroll_rendered(render_tx, selector, top);
+                true
+            }
+        }
+    }
+}
+
+/// Move the viewport matching `selector` once `Entry` patched the rows around the index in.
+#[cfg(target_arch = "wasm32")]
+fn scroll_rendered(render_tx: &Sender<((), oneshot::Sender<()>)>, selector: String, top: f64) {
+    let mut render_tx = render_tx.clone();
+    spawn_local(async move {
+        let (tx, rx) = oneshot::channel();
+        let _ = render_tx.send(((), tx)).await;
+        if rx.await.is_err() {
+            return;
+        }
+        let element = web_sys::window()
+            .and_then(|window| window.document())
+            .and_then(|document| document.query_selector(&selector).ok().flatten());
+        if let Some(element) = element {
+            element.set_scroll_top(top as i32);
+        }
+    });
+}
+
+/// Off wasm there's no viewport, scrolling only updates the list's state.
+#[cfg(not(target_arch = "wasm32"))]
+fn scroll_rendered(_render_tx: &Sender<((), oneshot::Sender<()>)>, _selector: String, _top: f64) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixed_rows_window() {
+        let mut list = VirtualList::new("rows", 50_000, RowHeights::Fixed(20.0), 100.0).overscan(2);
+        assert_eq!(list.total_height(), 1_000_000.0);
+        assert_eq!(list.visible_rows(), 0..8);
+
+        assert!(list.set_scroll(1000.0, 100.0));
+        assert_eq!(list.visible_rows(), 48..58);
+        assert!(!list.set_scroll(1005.0, 100.0));
+
+        assert_eq!(list.scroll_to_index(49_999), 1_000_000.0 - 100.0);
+        assert_eq!(list.visible_rows(), 49_993..50_000);
+    }
+
+    #[test]
+    fn variable_rows_window() {
+        let heights = RowHeights::Variable(vec![10.0, 50.0, 20.0, 20.0, 100.0]);
+        let mut list = VirtualList::new("rows", 8, heights, 60.0).overscan(0);
+        assert_eq!(list.total_height(), 500.0);
+        assert_eq!(list.row_at(9.0), 0);
+        assert_eq!(list.row_at(10.0), 1);
+        assert_eq!(list.row_at(85.0), 3);
+        assert_eq!(list.row_at(1000.0), 7);
+
+        list.set_scroll(60.0, 60.0);
+        assert_eq!(list.visible_rows(), 2..5);
+    }
+
+    #[test]
+    fn grid_rows() {
+        let mut list = VirtualList::new("grid", 10, RowHeights::Fixed(10.0), 20.0)
+            .columns(3)
+            .overscan(0);
+        assert_eq!(list.rows(), 4);
+        list.set_scroll(20.0, 20.0);
+        assert_eq!(list.visible_items(), 6..10);
+        assert_eq!(list.scroll_to_index(4), 10.0);
+    }
+
+    #[test]
+    fn scroll_to_without_document() {
+        let (sender, _) = mpsc::unbounded();
+        let (render_tx, mut renders) = mpsc::unbounded();
+        let mut list = VirtualList::new("rows", 100, RowHeights::Fixed(20.0), 100.0).overscan(0);
+
+        let msg = Box::new(VirtualMsg::scroll_to(|list| list, 50));
+        assert!(msg.update(&mut list, &sender, &render_tx));
+        assert_eq!(list.visible_rows(), 50..56);
+        assert!(renders.try_recv().is_err());
+    }
+}
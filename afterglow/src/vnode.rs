@@ -0,0 +1,222 @@
+use crate::backend::Dodrio;
+use crate::prelude::*;
+use dodrio::bumpalo::collections::Vec as BumpVec;
+use std::borrow::Cow;
+use std::rc::Rc;
+
+pub type Str = Cow<'static, str>;
+pub type Listener = Rc<dyn Fn(Event)>;
+
+/// Backend independent description of a view, turned into real nodes by a `Backend`.
+pub enum VNode {
+    Element(VElement),
+    Text(Str),
+    /// An embedded container, rendered with it's own cache where the backend supports it.
+    Component(Rc<dyn Component>),
+    /// Siblings without a wrapping element, spliced into the parent's children.
+    Fragment(Vec<VNode>),
+}
+
+pub struct VElement {
+    pub tag: Str,
+    pub namespace: Option<Str>,
+    pub key: Option<u32>,
+    pub attrs: Vec<(Str, Str)>,
+    pub listeners: Vec<(Str, Listener)>,
+    pub children: Vec<VNode>,
+}
+
+/// A subtree owned by something else, usually a child `Container`.
+pub trait Component {
+    /// Render into dodrio, reusing cached output when possible.
+    fn dodrio<'a>(&self, cx: &mut RenderContext<'a>) -> Node<'a>;
+
+    /// Nodes to splice into the parent, the subtree's fragment if it has one.
+    fn dodrio_children<'a>(&self, cx: &mut RenderContext<'a>) -> BumpVec<'a, Node<'a>> {
+        let mut nodes = BumpVec::new_in(cx.bump);
+        nodes.push(self.dodrio(cx));
+        nodes
+    }
+
+    /// Backend independent view, `None` if the subtree can only be rendered by dodrio.
+    fn vnode(&self) -> Option<VNode>;
+}
+
+/// Start building an element with given tag name.
+pub fn el(tag: impl Into<Str>) -> ElementBuilder {
+    ElementBuilder {
+        element: VElement {
+            tag: tag.into(),
+            namespace: None,
+            key: None,
+            attrs: vec![],
+            listeners: vec![],
+            children: vec![],
+        },
+    }
+}
+
+pub fn text(content: impl Into<Str>) -> VNode {
+    VNode::Text(content.into())
+}
+
+/// Several nodes without a wrapping element.
+pub fn fragment(children: impl IntoIterator<Item = VNode>) -> VNode {
+    VNode::Fragment(children.into_iter().collect())
+}
+
+impl VNode {
+    /// Replace embedded components by their backend independent view, so nested containers render
+    /// while their parent is still the container being rendered.
+    pub(crate) fn expand(self) -> VNode {
+        match self {
+            VNode::Component(component) => match component.vnode() {
+                Some(node) => node,
+                None => VNode::Component(component),
+            },
+            VNode::Element(mut element) => {
+                element.children = element.children.into_iter().map(VNode::expand).collect();
+                VNode::Element(element)
+            }
+            VNode::Fragment(children) => {
+                VNode::Fragment(children.into_iter().map(VNode::expand).collect())
+            }
+            node => node,
+        }
+    }
+}
+
+/// convert a msg into a listener, like `consume` does for dodrio's builders.
+pub fn consume<T, M>(convert: impl Fn(Event) -> M + 'static, sender: &MessageSender<T>) -> Listener
+where
+    M: Messenger<Target = T> + 'static,
+    T: 'static,
+{
+    let sender = sender.clone();
+    Rc::new(move |event| convert(event).send(&sender))
+}
+
+pub struct ElementBuilder {
+    element: VElement,
+}
+
+impl ElementBuilder {
+    pub fn attr(mut self, name: impl Into<Str>, value: impl Into<Str>) -> Self {
+        self.element.attrs.push((name.into(), value.into()));
+        self
+    }
+
+    /// Add an attribute with an empty value if `should_add` is true.
+    pub fn bool_attr(self, name: impl Into<Str>, should_add: bool) -> Self {
+        if should_add {
+            self.attr(name, "")
+        } else {
+            self
+        }
+    }
+
+    /// Mark the element so `node_ref` can find it once rendered.
+    pub fn node_ref(self, node_ref: &NodeRef) -> Self {
+        self.attr(NodeRef::ATTR, node_ref.value())
+    }
+
+    pub fn key(mut self, key: u32) -> Self {
+        self.element.key = Some(key);
+        self
+    }
+
+    pub fn namespace(mut self, namespace: impl Into<Str>) -> Self {
+        self.element.namespace = Some(namespace.into());
+        self
+    }
+
+    pub fn on(mut self, event: impl Into<Str>, listener: impl Fn(Event) + 'static) -> Self {
+        self.element
+            .listeners
+            .push((event.into(), Rc::new(listener)));
+        self
+    }
+
+    /// Attach a listener that is already shared, such as the output of `consume`.
+    pub fn listener(mut self, event: impl Into<Str>, listener: Listener) -> Self {
+        self.element.listeners.push((event.into(), listener));
+        self
+    }
+
+    pub fn child(mut self, child: VNode) -> Self {
+        self.element.children.push(child);
+        self
+    }
+
+    pub fn children(mut self, children: impl IntoIterator<Item = VNode>) -> Self {
+        self.element.children.extend(children);
+        self
+    }
+
+    pub fn finish(self) -> VNode {
+        VNode::Element(self.element)
+    }
+}
+
+/// A renderer that doesn't depend on a specific vdom backend.
+///
+/// Every `View` is also a `Renderer`, rendered through dodrio in the browser and into html
+/// strings with `render_to_string` elsewhere.
+pub trait View {
+    type Target;
+    type Data;
+
+    fn render(&self, target: &Self::Target, sender: &MessageSender<Self::Data>) -> VNode;
+
+    /// Whether `render` returns a fragment to splice into the parent's children.
+    ///
+    /// Decided up front so views with a single root are rendered once and keep using the
+    /// container's cache, fragments are rebuilt whenever the parent renders.
+    fn is_fragment(&self) -> bool {
+        false
+    }
+
+    /// See `Renderer::style`.
+    fn style(&self) -> Option<&'static Style> {
+        None
+    }
+}
+
+impl<V> Renderer for V
+where
+    V: View,
+{
+    type Target = V::Target;
+    type Data = V::Data;
+
+    fn view<'a>(
+        &self,
+        target: &Self::Target,
+        ctx: &mut RenderContext<'a>,
+        sender: &MessageSender<Self::Data>,
+    ) -> Node<'a> {
+        let node = View::render(self, target, sender);
+        Dodrio::new(ctx).build(node)
+    }
+
+    fn view_children<'a>(
+        &self,
+        target: &Self::Target,
+        ctx: &mut RenderContext<'a>,
+        sender: &MessageSender<Self::Data>,
+    ) -> Option<BumpVec<'a, Node<'a>>> {
+        if !View::is_fragment(self) {
+            return None;
+        }
+        let node = View::render(self, target, sender);
+        Some(Dodrio::new(ctx).build_children(node))
+    }
+
+    fn vnode(&self, target: &Self::Target, sender: &MessageSender<Self::Data>) -> Option<VNode> {
+        Some(View::render(self, target, sender))
+    }
+
+    fn style(&self) -> Option<&'static Style> {
+        View::style(self)
+    }
+}